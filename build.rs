@@ -0,0 +1,26 @@
+//! build script embeds the version of aurders, along with the commit it is built from when built
+//! from a git checkout
+use std::env;
+use std::process::Command;
+
+fn main() {
+    let version = env::var("CARGO_PKG_VERSION").unwrap_or_default();
+
+    // crates.io tarballs and the like have no git history, version alone has to do
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .filter(|commit| !commit.is_empty());
+
+    match commit {
+        Some(commit) => println!("cargo:rustc-env=AURDERS_VERSION={} ({})", version, commit),
+        None => println!("cargo:rustc-env=AURDERS_VERSION={}", version),
+    };
+
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
@@ -1,23 +1,145 @@
 //! args module handles arguments
+use std::collections::BTreeMap;
+use std::io;
 use std::path::PathBuf;
+use std::process::exit;
+use std::time::Duration;
 
 use clap::{value_parser, Arg, ArgAction, Command};
+use clap_complete::{generate, Shell};
 
-use crate::utils::dead;
+use crate::build_system::BuildSystem;
+use crate::bump::Bump;
+use crate::checksum::ChecksumAlgorithm;
+use crate::config::Config;
+use crate::error::AurdersError;
+use crate::preset::Preset;
+use crate::utils::{is_placeholder_name, parse_list, print_licenses, FetchOptions, TarballFormat};
 
-/// handle_args handles the arguments
-pub fn handle_args() -> (PathBuf, bool) {
-    let matches = Command::new("aurders")
+/// Args stores the arguments aurders was run with
+pub struct Args {
+    pub source: PathBuf,
+    pub get_template: bool,
+    pub config: Option<PathBuf>,
+    pub write_config: Option<PathBuf>,
+    /// file to write the collected package information to as JSON, - being stdout
+    pub emit_json: Option<PathBuf>,
+    /// existing PKGBUILD to take package information from, to regenerate it with some changes
+    pub from_pkgbuild: Option<PathBuf>,
+    /// directory with custom PKGBUILD and SRCINFO templates, embedded ones are used otherwise
+    pub template_dir: Option<PathBuf>,
+    /// values of custom placeholders in templates, by their names
+    pub template_vars: Vec<(String, String)>,
+    /// prebuilt release assets package is made of, each for an architecture or for all of them
+    pub bin: Vec<(Option<String>, String)>,
+    /// directory where tarball, PKGBUILD and .SRCINFO are generated
+    pub output_dir: PathBuf,
+    /// how downloads are made
+    pub fetch: FetchOptions,
+    /// compression of tarball
+    pub tarball_format: TarballFormat,
+    /// compression level of tarball, from 0 to 9
+    pub compression: u32,
+    /// keep VCS metadata directories (.git, .svn, ...) in tarball
+    pub include_vcs: bool,
+    /// gitignore-like patterns of files left out of tarball
+    pub exclude: Vec<String>,
+    /// id of PGP key tarball is signed with
+    pub sign: Option<String>,
+    /// overwrite existing PKGBUILD and .SRCINFO without asking
+    pub force: bool,
+    /// rewrite only the generated fields of an existing PKGBUILD, keeping its functions
+    pub merge: bool,
+    /// leave the output directory and tarball created so far in place when aurders fails
+    pub keep_on_error: bool,
+    /// make output_dir a git repository to push to AUR, instead of building package and cloning
+    /// the AUR repository
+    pub git_init: bool,
+    /// commit the files in repository made by git_init and push them to AUR
+    pub publish: bool,
+    /// read answers as lines from stdin without showing prompts
+    pub batch: bool,
+    /// show prompts as select menus, confirmations and inputs validated inline
+    pub tui: bool,
+    /// remember the maintainer of this run as default of the next ones
+    pub save_maintainer: bool,
+    /// only print warnings and errors, not the status messages
+    pub quiet: bool,
+    /// also print debug messages, like every substitution made in templates
+    pub verbose: bool,
+    /// print PKGBUILD and .SRCINFO instead of writing anything to disk
+    pub dry_run: bool,
+    /// check the generated PKGBUILD with makepkg
+    pub verify: bool,
+    /// lint the generated PKGBUILD with namcap
+    pub lint: bool,
+    /// skip reviewing the package information before generating files
+    pub yes: bool,
+    /// type of package whose defaults the prompts start from
+    pub preset: Option<Preset>,
+    /// package information given as flags, these take precedence over config
+    pub fields: Config,
+    /// change to make to the existing package in output_dir, instead of generating a new one
+    pub bump: Option<Bump>,
+    /// check that .SRCINFO in output_dir is up to date with its PKGBUILD, instead of generating
+    /// a new package
+    pub check: bool,
+}
+
+/// field_arg returns the flag for a field of package information, named after the field
+fn field_arg(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name)
+        .long(name)
+        .help(help)
+        .value_parser(value_parser!(String))
+}
+
+/// VERSION is the version of aurders, followed by the commit it is built from if known
+pub const VERSION: &str = env!("AURDERS_VERSION");
+
+/// build_cli returns the command line interface of aurders
+pub fn build_cli() -> Command {
+    Command::new("aurders")
         // Will be shown only when custom help template is used (on clap 4.0 or later)
         // .author("Mitesh Soni, smiteshhc@gmail.com")
-        .version("1.0.0")
+        .version(VERSION)
         .about("aurders is a simple aur helper for developers to publish their packages easily on Arch User Repository.")
+        .subcommand_negates_reqs(true)
+        .subcommand(
+            Command::new("bump-version")
+                .about("Raise pkgver of the package in output directory, resetting pkgrel to 1")
+                .arg(
+                    Arg::new("pkgver")
+                        .required(true)
+                        .help("New version of package")
+                        .value_parser(value_parser!(String))
+                )
+        )
+        .subcommand(
+            Command::new("bump-rel")
+                .about("Increment pkgrel of the package in output directory for a rebuild")
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Check that .SRCINFO of the package in output directory matches its PKGBUILD, e.g. in CI")
+        )
+        .subcommand(Command::new("version").about("Print the version of aurders"))
+        .subcommand(
+            Command::new("completions")
+                .about("Print the completion script of aurders for shell")
+                .arg(
+                    Arg::new("shell")
+                        .required(true)
+                        .help("Shell to complete in")
+                        .value_parser(value_parser!(Shell))
+                )
+        )
         .arg(
             Arg::new("source")
                 // Do not set short() or long() as we want to define positional argument
                 // .short('s')
                 // .long("source")
-                .required(true)
+                .required_unless_present("list-licenses")
                 .help("Source folder of the packages")
                 .value_parser(value_parser!(PathBuf))
         )
@@ -26,31 +148,561 @@ pub fn handle_args() -> (PathBuf, bool) {
             Arg::new("templates")
                 .short('t')
                 .long("templates")
-                .help("Get templates")
+                .help("Download the default templates into templates/, e.g. to customize them for --template-dir")
                 .action(ArgAction::SetTrue)
                 .default_missing_value("true")
                 .value_parser(value_parser!(bool))
         )
-        .get_matches();
+        .arg(
+            Arg::new("list-licenses")
+                .long("list-licenses")
+                .help("List the common license identifiers, which the license prompt also takes by number")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .help("Read package information from a TOML or JSON file, prompting only for what it lacks")
+                .value_parser(value_parser!(PathBuf))
+        )
+        .arg(
+            Arg::new("write-config")
+                .long("write-config")
+                .help("Write the collected package information to a TOML or JSON file for reuse")
+                .value_parser(value_parser!(PathBuf))
+        )
+        .arg(
+            Arg::new("emit-json")
+                .long("emit-json")
+                .value_name("PATH")
+                .help("Write every field of the package, checksums included, as JSON to PATH (--emit-json=PATH), or stdout when it is - or left out")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("-")
+                .value_parser(value_parser!(PathBuf))
+        )
+        .arg(
+            Arg::new("from-pkgbuild")
+                .long("from-pkgbuild")
+                .help("Take package information from an existing PKGBUILD, e.g. to change only its version")
+                .value_parser(value_parser!(PathBuf))
+        )
+        .arg(
+            Arg::new("template-dir")
+                .long("template-dir")
+                .help("Directory with custom PKGBUILD and SRCINFO templates to use instead of the built-in ones")
+                .value_parser(value_parser!(PathBuf))
+        )
+        .arg(
+            Arg::new("template-var")
+                .long("template-var")
+                .value_name("NAME=VALUE")
+                .help("Value of a custom {NAME} placeholder in templates, can be given multiple times")
+                .action(ArgAction::Append)
+                .value_parser(value_parser!(String))
+        )
+        .arg(
+            Arg::new("output-dir")
+                .short('o')
+                .long("output-dir")
+                .help("Directory to generate tarball, PKGBUILD and .SRCINFO in")
+                .default_value("aurders")
+                .global(true)
+                .value_parser(value_parser!(PathBuf))
+        )
+        .arg(
+            Arg::new("retries")
+                .long("retries")
+                .help("Times to retry a failed download, waiting longer before each")
+                .default_value("3")
+                .global(true)
+                .value_parser(value_parser!(u32))
+        )
+        .arg(
+            Arg::new("timeout")
+                .long("timeout")
+                .help("Seconds to wait for a download to connect or send data")
+                .default_value("30")
+                .global(true)
+                .value_parser(value_parser!(u64))
+        )
+        .arg(
+            Arg::new("offline")
+                .long("offline")
+                .help("Do not download anything, remote sources get 'SKIP' as checksum")
+                .global(true)
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("tarball-format")
+                .long("tarball-format")
+                .help("Compression of tarball")
+                .default_value("gzip")
+                .value_parser(["gzip", "xz", "zstd"])
+        )
+        .arg(
+            Arg::new("compression")
+                .long("compression")
+                .help("Compression level of tarball")
+                .default_value("default")
+                .value_parser(["fast", "default", "best", "0", "1", "2", "3", "4", "5", "6", "7", "8", "9"])
+        )
+        .arg(
+            Arg::new("include-vcs")
+                .long("include-vcs")
+                .help("Keep VCS metadata directories (.git, .svn, ...) in tarball")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("exclude")
+                .long("exclude")
+                .help("Leave files matching pattern (e.g. target/, *.o) out of tarball, can be given multiple times")
+                .action(ArgAction::Append)
+                .value_parser(value_parser!(String))
+        )
+        .arg(
+            Arg::new("sign")
+                .long("sign")
+                .value_name("KEYID")
+                .help("Sign tarball with the PGP key of KEYID, adding the signature to sources and the key to validpgpkeys")
+                .value_parser(value_parser!(String))
+        )
+        .arg(
+            Arg::new("force")
+                .short('f')
+                .long("force")
+                .help("Overwrite existing PKGBUILD and .SRCINFO without asking")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("merge")
+                .long("merge")
+                .help("Update only the fields of an existing PKGBUILD, keeping its functions, comments and other lines")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("keep-on-error")
+                .long("keep-on-error")
+                .help("Keep the output directory and tarball created so far when aurders fails")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("git-init")
+                .long("git-init")
+                .help("Make output directory a git repository with the AUR remote, without committing")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("publish")
+                .long("publish")
+                .help("Like --git-init, then commit the files and push them to AUR after confirming")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("batch")
+                .long("batch")
+                .help("Read answers from stdin, one per line in the documented order, without prompts")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("tui")
+                .long("tui")
+                .help("Ask with select menus and confirmations instead of plain prompts")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("batch")
+        )
+        .arg(
+            Arg::new("save-maintainer")
+                .long("save-maintainer")
+                .help("Remember the maintainer name and email of this run as defaults of the next ones")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help("Do not print status messages, only warnings and errors")
+                .global(true)
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Print debug messages, like every placeholder substituted in templates")
+                .global(true)
+                .conflicts_with("quiet")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("dry-run")
+                .short('n')
+                .long("dry-run")
+                .help("Print the generated PKGBUILD and .SRCINFO without writing anything to disk")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("verify")
+                .long("verify")
+                .help("Check the generated PKGBUILD with makepkg --printsrcinfo, if makepkg is installed")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("lint")
+                .long("lint")
+                .help("Lint the generated PKGBUILD with namcap, failing on its errors")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .help("Generate files without reviewing the package information first")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("preset")
+                .long("preset")
+                .help("Start from the dependencies and build system of a common type of package")
+                .value_parser(["cli-tool", "python-app", "rust-bin", "git"])
+        )
+        .arg(field_arg("maintainer-name", "Name of maintainer"))
+        .arg(field_arg("maintainer-email", "Email of maintainer"))
+        .arg(field_arg("pkgname", "Name of package, separated by commas or spaces for split package"))
+        .arg(field_arg("pkgbase", "Base name of split package"))
+        .arg(field_arg("pkgver", "Version of package"))
+        .arg(field_arg("pkgrel", "Release number of package"))
+        .arg(field_arg("epoch", "Epoch of package"))
+        .arg(field_arg("pkgdesc", "Description about package"))
+        .arg(field_arg("url", "Url of package"))
+        .arg(field_arg("license", "License of package"))
+        .arg(field_arg("arch", "Target architecture(s) of package, separated by commas or spaces"))
+        .arg(field_arg("depends", "Dependencies of package, separated by commas or spaces"))
+        .arg(field_arg("makedepends", "Make dependencies of package, separated by commas or spaces"))
+        .arg(field_arg("checkdepends", "Dependencies needed only to run the test suite in check()"))
+        .arg(field_arg("optdepends", "Optional dependencies of package, separated by semicolons"))
+        .arg(field_arg("provides", "What package provides, separated by commas or spaces"))
+        .arg(field_arg("conflicts", "Packages conflicting with package, separated by commas or spaces"))
+        .arg(field_arg("replaces", "Packages replaced by package, separated by commas or spaces"))
+        .arg(field_arg("groups", "Groups package belongs to, separated by commas or spaces"))
+        .arg(field_arg("install", "Install scriptlet of package, e.g. foo.install"))
+        .arg(field_arg("changelog", "Changelog file of package, e.g. foo.changelog"))
+        .arg(field_arg("backup", "Files pacman keeps across upgrades, separated by commas or spaces"))
+        .arg(field_arg("options", "makepkg options of package, separated by commas or spaces"))
+        .arg(
+            Arg::new("vcs")
+                .long("vcs")
+                .help("Package is a VCS package, versioned from git")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("service")
+                .long("service")
+                .help("Package provides a systemd service, scaffolded as <pkgname>.service among sources")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            // id differs from the flag, "source" is already the source folder
+            Arg::new("sources")
+                .long("source")
+                .help("Source of package, can be given multiple times")
+                .action(ArgAction::Append)
+                .value_parser(value_parser!(String))
+        )
+        .arg(field_arg(
+            "noextract",
+            "File names of the sources makepkg should not extract, separated by commas or spaces",
+        ))
+        .arg(field_arg(
+            "validpgpkeys",
+            "Fingerprints of PGP keys signing the sources, separated by commas or spaces",
+        ))
+        .arg(
+            Arg::new("arch-source")
+                .long("arch-source")
+                .value_name("ARCH=SOURCE")
+                .help("Source specific to an architecture (source_<arch>), can be given multiple times")
+                .action(ArgAction::Append)
+                .value_parser(value_parser!(String))
+        )
+        .arg(
+            Arg::new("bin")
+                .long("bin")
+                .value_name("[ARCH=]URL")
+                .help("Prebuilt release asset to make a -bin package of, installed to /usr/bin; can be given once for each architecture")
+                .action(ArgAction::Append)
+                .value_parser(value_parser!(String))
+        )
+        .arg(
+            Arg::new("checksum")
+                .long("checksum")
+                .help("Checksum algorithm for sources")
+                .value_parser(["sha256", "sha512", "b2"])
+        )
+        .arg(
+            Arg::new("build-system")
+                .long("build-system")
+                .help("Build system of package, filling build() and package() with its skeleton")
+                .value_parser(["none", "make", "cmake", "meson", "python", "cargo"])
+        )
+        .arg(field_arg("prepare", "Commands of prepare()"))
+        .arg(field_arg("build", "Commands of build()"))
+        .arg(
+            field_arg("package", "Commands of package(), given once for each of the split packages")
+                .action(ArgAction::Append)
+        )
+}
+
+/// handle_args handles the arguments
+pub fn handle_args() -> Result<Args, AurdersError> {
+    let matches = build_cli().get_matches();
+
+    // version, completion script and license list are all there is to do, there is no package to speak of
+    if let Some(("version", _)) = matches.subcommand() {
+        println!("aurders {}", VERSION);
+        exit(0);
+    }
+
+    if matches.get_flag("list-licenses") {
+        print_licenses();
+        exit(0);
+    }
+
+    if let Some(("completions", completions)) = matches.subcommand() {
+        let shell = *completions
+            .get_one::<Shell>("shell")
+            .expect("Shell is not specified. See --help.");
+        generate(shell, &mut build_cli(), "aurders", &mut io::stdout());
+        exit(0);
+    }
+
+    let bump = match matches.subcommand() {
+        Some(("bump-version", bump)) => Some(Bump::Version(
+            bump.get_one::<String>("pkgver")
+                .expect("New version is not specified. See --help.")
+                .clone(),
+        )),
+        Some(("bump-rel", _)) => Some(Bump::Release),
+        _ => None,
+    };
+    let check = matches!(matches.subcommand(), Some(("check", _)));
 
+    // source directory is only needed to generate a new package
     let source = matches
         .get_one::<PathBuf>("source")
-        .expect("Source folder is not specified. See --help.");
+        .cloned()
+        .unwrap_or_default();
 
     let get_template = matches
         .get_one("templates")
         .expect("Failed to get flag templates");
 
-    if !source.is_dir() {
-        eprintln!("Source is not a directory.");
-        eprintln!("Source must be a directory.");
-        dead();
+    let existing = bump.is_some() || check;
+
+    if !existing && !source.is_dir() {
+        return Err(AurdersError::Failed(
+            "Source is not a directory, it must be one".to_string(),
+        ));
     }
 
-    if !source.exists() {
-        eprintln!("Provided source does not exists, or cannot access its metadata.");
-        dead();
+    if !existing && !source.exists() {
+        return Err(AurdersError::Failed(
+            "Provided source does not exists, or cannot access its metadata".to_string(),
+        ));
     }
 
-    (source.to_path_buf(), *get_template)
+    let field = |name: &str| matches.get_one::<String>(name).cloned();
+    let many = |name: &str| {
+        matches
+            .get_many::<String>(name)
+            .map(|values| values.cloned().collect::<Vec<String>>())
+    };
+
+    let mut template_vars: Vec<(String, String)> = Vec::new();
+    for value in many("template-var").unwrap_or_default() {
+        match value.split_once('=') {
+            Some((name, value)) if is_placeholder_name(name) => {
+                template_vars.push((name.to_string(), value.to_string()))
+            }
+            _ => {
+                return Err(AurdersError::Failed(format!(
+                    "Invalid --template-var '{}': expected NAME=VALUE, NAME being lowercase letters, digits and underscores",
+                    value
+                )))
+            }
+        }
+    }
+
+    let arch_source = match many("arch-source") {
+        Some(values) => {
+            let mut arch_source: BTreeMap<String, Vec<String>> = BTreeMap::new();
+            for value in values {
+                match value.split_once('=') {
+                    Some((arch, source)) => arch_source
+                        .entry(arch.trim().to_string())
+                        .or_default()
+                        .push(source.trim().to_string()),
+                    None => {
+                        return Err(AurdersError::Failed(format!(
+                            "Invalid --arch-source '{}': expected ARCH=SOURCE",
+                            value
+                        )))
+                    }
+                }
+            }
+            Some(arch_source)
+        }
+        None => None,
+    };
+
+    let fields = Config {
+        maintainer_name: field("maintainer-name"),
+        maintainer_email: field("maintainer-email"),
+        pkgbase: field("pkgbase"),
+        pkgname: field("pkgname").map(|pkgname| parse_list(&pkgname)),
+        pkgver: field("pkgver"),
+        pkgrel: field("pkgrel"),
+        epoch: field("epoch"),
+        pkgdesc: field("pkgdesc"),
+        url: field("url"),
+        license: field("license"),
+        arch: field("arch").map(|arch| parse_list(&arch)),
+        depends: field("depends"),
+        makedepends: field("makedepends"),
+        checkdepends: field("checkdepends"),
+        optdepends: field("optdepends"),
+        provides: field("provides"),
+        conflicts: field("conflicts"),
+        replaces: field("replaces"),
+        groups: field("groups"),
+        install: field("install"),
+        changelog: field("changelog"),
+        backup: field("backup"),
+        options: field("options"),
+        // unset flag leaves the decision to config or prompt
+        vcs: matches.get_flag("vcs").then_some(true),
+        service: matches.get_flag("service").then_some(true),
+        source: many("sources"),
+        noextract: field("noextract"),
+        validpgpkeys: field("validpgpkeys"),
+        arch_source,
+        checksum_algorithm: field("checksum").and_then(|name| ChecksumAlgorithm::from_name(&name)),
+        build_system: field("build-system").and_then(|name| BuildSystem::from_name(&name)),
+        prepare: field("prepare"),
+        build: field("build"),
+        package: many("package"),
+    };
+
+    Ok(Args {
+        source: source.to_path_buf(),
+        get_template: *get_template,
+        config: matches.get_one::<PathBuf>("config").cloned(),
+        write_config: matches.get_one::<PathBuf>("write-config").cloned(),
+        emit_json: matches.get_one::<PathBuf>("emit-json").cloned(),
+        from_pkgbuild: matches.get_one::<PathBuf>("from-pkgbuild").cloned(),
+        template_dir: matches.get_one::<PathBuf>("template-dir").cloned(),
+        template_vars,
+        // = in the query of an URL does not make what precedes it an architecture
+        bin: many("bin")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|value| match value.split_once('=') {
+                Some((arch, url)) if !arch.contains([':', '/']) => {
+                    (Some(arch.trim().to_string()), url.trim().to_string())
+                }
+                _ => (None, value),
+            })
+            .collect(),
+        output_dir: matches
+            .get_one::<PathBuf>("output-dir")
+            .expect("Failed to get output directory")
+            .to_path_buf(),
+        fetch: FetchOptions {
+            retries: *matches.get_one::<u32>("retries").unwrap_or(&3),
+            timeout: Duration::from_secs(*matches.get_one::<u64>("timeout").unwrap_or(&30)),
+            offline: matches.get_flag("offline"),
+        },
+        tarball_format: field("tarball-format")
+            .and_then(|name| TarballFormat::from_name(&name))
+            .unwrap_or_default(),
+        compression: match field("compression").as_deref() {
+            Some("fast") => 1,
+            Some("best") => 9,
+            // anything else clap accepts is a level from 0 to 9
+            Some("default") | None => 6,
+            Some(level) => level.parse().unwrap_or(6),
+        },
+        include_vcs: matches.get_flag("include-vcs"),
+        exclude: many("exclude").unwrap_or_default(),
+        sign: field("sign"),
+        force: matches.get_flag("force"),
+        merge: matches.get_flag("merge"),
+        keep_on_error: matches.get_flag("keep-on-error"),
+        // publishing needs the repository --git-init makes
+        git_init: matches.get_flag("git-init") || matches.get_flag("publish"),
+        publish: matches.get_flag("publish"),
+        batch: matches.get_flag("batch"),
+        tui: matches.get_flag("tui"),
+        save_maintainer: matches.get_flag("save-maintainer"),
+        quiet: matches.get_flag("quiet"),
+        verbose: matches.get_flag("verbose"),
+        dry_run: matches.get_flag("dry-run"),
+        verify: matches.get_flag("verify"),
+        lint: matches.get_flag("lint"),
+        yes: matches.get_flag("yes"),
+        preset: field("preset").and_then(|name| Preset::from_name(&name)),
+        fields,
+        bump,
+        check,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emit_json_leaves_source_alone() {
+        let matches = build_cli()
+            .try_get_matches_from(["aurders", "--emit-json", "source_dir"])
+            .unwrap();
+
+        assert_eq!(
+            matches.get_one::<PathBuf>("emit-json"),
+            Some(&PathBuf::from("-"))
+        );
+        assert_eq!(
+            matches.get_one::<PathBuf>("source"),
+            Some(&PathBuf::from("source_dir"))
+        );
+    }
+
+    #[test]
+    fn emit_json_takes_path_after_equals() {
+        let matches = build_cli()
+            .try_get_matches_from(["aurders", "--emit-json=info.json", "source_dir"])
+            .unwrap();
+
+        assert_eq!(
+            matches.get_one::<PathBuf>("emit-json"),
+            Some(&PathBuf::from("info.json"))
+        );
+    }
+
+    #[test]
+    fn bash_completion_knows_the_flags() {
+        let mut buf = Vec::new();
+        generate(Shell::Bash, &mut build_cli(), "aurders", &mut buf);
+        let script = String::from_utf8(buf).unwrap();
+
+        for flag in [
+            "--output-dir",
+            "--pkgver",
+            "--tarball-format",
+            "completions",
+        ] {
+            assert!(script.contains(flag), "{} is missing", flag);
+        }
+    }
 }
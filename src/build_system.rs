@@ -0,0 +1,123 @@
+//! build_system module holds the skeletons of build() and package() for common build systems
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AurdersError;
+use crate::tui;
+use crate::utils::{fail_in_batch, print_prompt, read_input, tui};
+
+/// BuildSystem is the build system package uses, None leaves build() and package() to the user
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildSystem {
+    #[default]
+    None,
+    Make,
+    Cmake,
+    Meson,
+    Python,
+    Cargo,
+}
+
+impl BuildSystem {
+    /// from_name returns the build system of given name
+    pub fn from_name(name: &str) -> Option<BuildSystem> {
+        match name {
+            "none" => Some(BuildSystem::None),
+            "make" => Some(BuildSystem::Make),
+            "cmake" => Some(BuildSystem::Cmake),
+            "meson" => Some(BuildSystem::Meson),
+            "python" => Some(BuildSystem::Python),
+            "cargo" => Some(BuildSystem::Cargo),
+            _ => None,
+        }
+    }
+
+    /// build returns the skeleton body of build() for sources extracted to srcdir
+    pub fn build(&self, srcdir: &str) -> String {
+        let commands = match self {
+            BuildSystem::None => vec![],
+            BuildSystem::Make => vec![format!("cd \"{}\"", srcdir), "make".to_string()],
+            BuildSystem::Cmake => vec![
+                format!(
+                    "cmake -B build -S \"{}\" -DCMAKE_BUILD_TYPE='None' -DCMAKE_INSTALL_PREFIX='/usr' -Wno-dev",
+                    srcdir
+                ),
+                "cmake --build build".to_string(),
+            ],
+            BuildSystem::Meson => vec![
+                format!("arch-meson \"{}\" build", srcdir),
+                "meson compile -C build".to_string(),
+            ],
+            BuildSystem::Python => vec![
+                format!("cd \"{}\"", srcdir),
+                "python -m build --wheel --no-isolation".to_string(),
+            ],
+            BuildSystem::Cargo => vec![
+                format!("cd \"{}\"", srcdir),
+                "export RUSTUP_TOOLCHAIN=stable".to_string(),
+                "export CARGO_TARGET_DIR=target".to_string(),
+                "cargo build --release --locked".to_string(),
+            ],
+        };
+
+        commands.join("\n    ")
+    }
+
+    /// package returns the skeleton body of package() for sources extracted to srcdir
+    pub fn package(&self, srcdir: &str) -> String {
+        let commands = match self {
+            BuildSystem::None => vec![],
+            BuildSystem::Make => vec![
+                format!("cd \"{}\"", srcdir),
+                "make DESTDIR=\"$pkgdir\" install".to_string(),
+            ],
+            BuildSystem::Cmake => vec!["DESTDIR=\"$pkgdir\" cmake --install build".to_string()],
+            BuildSystem::Meson => vec!["meson install -C build --destdir \"$pkgdir\"".to_string()],
+            BuildSystem::Python => vec![
+                format!("cd \"{}\"", srcdir),
+                "python -m installer --destdir=\"$pkgdir\" dist/*.whl".to_string(),
+            ],
+            BuildSystem::Cargo => vec![
+                format!("cd \"{}\"", srcdir),
+                "install -Dm0755 -t \"$pkgdir/usr/bin/\" \"target/release/$pkgname\"".to_string(),
+            ],
+        };
+
+        commands.join("\n    ")
+    }
+}
+
+/// select_build_system allows user to choose the build system of package, none being the default
+pub fn select_build_system() -> Result<BuildSystem, AurdersError> {
+    if tui() {
+        return tui::select_build_system();
+    }
+
+    print_prompt("\nSelect the build system of your package:\n");
+
+    loop {
+        print_prompt("  [1] none(Default)    [2] make    [3] cmake    [4] meson    [5] python    [6] cargo\n> ");
+        io::stdout().flush().unwrap();
+        let mut input = String::new();
+
+        read_input(&mut input)?;
+
+        // none as default, commands are entered manually
+        let build_system: u8 = input.trim().parse().unwrap_or(1);
+
+        match build_system {
+            1 => return Ok(BuildSystem::None),
+            2 => return Ok(BuildSystem::Make),
+            3 => return Ok(BuildSystem::Cmake),
+            4 => return Ok(BuildSystem::Meson),
+            5 => return Ok(BuildSystem::Python),
+            6 => return Ok(BuildSystem::Cargo),
+            _ => {
+                eprintln!("Invalid input. Try again");
+                fail_in_batch()?;
+            }
+        };
+    }
+}
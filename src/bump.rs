@@ -0,0 +1,316 @@
+//! bump module raises the version or release of an existing package in place, keeping its
+//! PKGBUILD and .SRCINFO in sync
+use std::fs;
+use std::path::Path;
+
+use crate::checksum::{source_checksums, ChecksumAlgorithm};
+use crate::error::AurdersError;
+use crate::pkgbuild::{parse_array, parse_pkgbuild, save_pkgbuild};
+use crate::shared::Information;
+use crate::srcinfo::save_srcinfo;
+use crate::utils::{format_array, validate_pkgver, FetchOptions};
+
+/// Bump is the change made to an existing package
+pub enum Bump {
+    /// new pkgver, pkgrel goes back to 1
+    Version(String),
+    /// pkgrel is incremented for a rebuild of the same version
+    Release,
+}
+
+/// bump_package applies bump to the PKGBUILD and .SRCINFO in output_dir, recomputing checksums of
+/// the sources whose name depends on the version (downloading remote ones as fetch says)
+pub fn bump_package(
+    bump: &Bump,
+    output_dir: &Path,
+    fetch: &FetchOptions,
+) -> Result<(), AurdersError> {
+    let read = |name: &str| {
+        fs::read_to_string(output_dir.join(name)).map_err(|e| {
+            AurdersError::Failed(format!(
+                "Failed to read {}: {}",
+                output_dir.join(name).display(),
+                e
+            ))
+        })
+    };
+    let mut pkgbuild = read("PKGBUILD")?;
+    let mut srcinfo = read(".SRCINFO")?;
+
+    let config = parse_pkgbuild(&pkgbuild)
+        .map_err(|e| AurdersError::Failed(format!("Failed to parse PKGBUILD: {}", e)))?;
+    let old_pkgrel = config.pkgrel.unwrap_or_default();
+
+    let (pkgver, pkgrel) = match bump {
+        Bump::Version(pkgver) => {
+            validate_pkgver(pkgver)
+                .map_err(|e| AurdersError::Failed(e.trim_end_matches('.').to_string()))?;
+            (pkgver.clone(), "1".to_string())
+        }
+        Bump::Release => {
+            // a rebuild of 1.1 is simply the next whole release
+            let release = old_pkgrel.split('.').next().unwrap_or_default();
+            match release.parse::<u32>() {
+                Ok(release) => (config.pkgver.unwrap_or_default(), (release + 1).to_string()),
+                Err(_) => {
+                    return Err(AurdersError::Failed(format!(
+                        "Invalid pkgrel '{}' in PKGBUILD",
+                        old_pkgrel
+                    )))
+                }
+            }
+        }
+    };
+
+    pkgbuild = set_assignment(&pkgbuild, "pkgver", &pkgver);
+    pkgbuild = set_assignment(&pkgbuild, "pkgrel", &pkgrel);
+    srcinfo = set_srcinfo_values(&srcinfo, "pkgver", std::slice::from_ref(&pkgver));
+    srcinfo = set_srcinfo_values(&srcinfo, "pkgrel", std::slice::from_ref(&pkgrel));
+
+    let checksum_algorithm = [
+        ChecksumAlgorithm::Sha256,
+        ChecksumAlgorithm::Sha512,
+        ChecksumAlgorithm::B2,
+    ]
+    .into_iter()
+    .find(|algorithm| parse_array(&pkgbuild, algorithm.array()).is_some())
+    .unwrap_or_default();
+
+    let pkgname = match config.pkgname {
+        Some(pkgname) if !pkgname.is_empty() => pkgname,
+        _ => return Err(AurdersError::Failed("PKGBUILD has no pkgname".to_string())),
+    };
+    let mut pkginfo = Information {
+        pkgbase: config
+            .pkgbase
+            .unwrap_or_else(|| pkgname.first().cloned().unwrap_or_default()),
+        pkgname,
+        pkgver,
+        pkgrel,
+        epoch: config.epoch.unwrap_or_default(),
+        url: config.url.unwrap_or_default(),
+        checksum_algorithm,
+        ..Default::default()
+    };
+
+    // source_<arch> arrays of the package follow the same versioning as the plain one
+    let arch = parse_array(&pkgbuild, "arch").unwrap_or_default();
+    let suffixes =
+        std::iter::once(String::new()).chain(arch.iter().map(|arch| format!("_{}", arch)));
+
+    for suffix in suffixes {
+        (pkgbuild, srcinfo) =
+            update_sources(pkgbuild, srcinfo, &mut pkginfo, &suffix, output_dir, fetch);
+    }
+
+    save_pkgbuild(&pkgbuild, output_dir, true)?;
+    save_srcinfo(&srcinfo, output_dir, true)
+}
+
+/// update_sources recomputes the checksums of the sources in source<suffix> array of pkgbuild whose
+/// name depends on the version, updating them in pkgbuild and srcinfo along with the sources of
+/// srcinfo. Nothing changes when there is no such array.
+fn update_sources(
+    mut pkgbuild: String,
+    mut srcinfo: String,
+    pkginfo: &mut Information,
+    suffix: &str,
+    output_dir: &Path,
+    fetch: &FetchOptions,
+) -> (String, String) {
+    let source_array = format!("source{}", suffix);
+    let checksum_array = format!("{}{}", pkginfo.checksum_algorithm.array(), suffix);
+
+    let source = match parse_array(&pkgbuild, &source_array) {
+        Some(source) => source,
+        None => return (pkgbuild, srcinfo),
+    };
+    let mut checksums = parse_array(&pkgbuild, &checksum_array).unwrap_or_default();
+
+    // only the sources named after the version are different files now
+    let changed: Vec<usize> = (0..source.len())
+        .filter(|&index| {
+            ["pkgver", "pkgrel"]
+                .iter()
+                .any(|variable| source[index].contains(variable))
+        })
+        .collect();
+    pkginfo.source = changed.iter().map(|&index| source[index].clone()).collect();
+    let new_checksums = source_checksums(pkginfo, output_dir, false, fetch);
+
+    for (index, checksum) in changed.iter().zip(new_checksums) {
+        if let Some(old) = checksums.get_mut(*index) {
+            // makepkg no longer verifies the source at all, worth knowing before publishing
+            if checksum == "SKIP" && *old != "SKIP" {
+                eprintln!(
+                    "Warning: checksum of {} could not be computed, it is 'SKIP' now.",
+                    pkginfo.expand_variables(&source[*index])
+                );
+            }
+            *old = checksum;
+        }
+    }
+
+    pkgbuild = set_array(&pkgbuild, &checksum_array, &checksums);
+    srcinfo = set_srcinfo_values(&srcinfo, &checksum_array, &checksums);
+
+    let expanded: Vec<String> = source
+        .iter()
+        .map(|source| pkginfo.expand_variables(source))
+        .collect();
+    srcinfo = set_srcinfo_values(&srcinfo, &source_array, &expanded);
+
+    (pkgbuild, srcinfo)
+}
+
+/// set_assignment sets the value of top level name=value line of PKGBUILD
+fn set_assignment(pkgbuild: &str, name: &str, value: &str) -> String {
+    let prefix = format!("{}=", name);
+
+    pkgbuild
+        .split_inclusive('\n')
+        .map(|line| {
+            if line.starts_with(&prefix) {
+                format!("{}{}\n", prefix, value)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+/// set_array replaces the values of name=(...) array of PKGBUILD, keeping the layout aurders
+/// generates
+fn set_array(pkgbuild: &str, name: &str, values: &[String]) -> String {
+    let prefix = format!("{}=(", name);
+    let start = match pkgbuild
+        .match_indices(&prefix)
+        .find(|(index, _)| *index == 0 || pkgbuild[..*index].ends_with('\n'))
+    {
+        Some((index, _)) => index,
+        None => return pkgbuild.to_string(),
+    };
+    let end = match pkgbuild[start..].find(')') {
+        Some(end) => start + end + 1,
+        None => return pkgbuild.to_string(),
+    };
+
+    format!(
+        "{}{}){}",
+        &pkgbuild[..start + prefix.len()],
+        format_array(values, '\'', prefix.len()),
+        &pkgbuild[end..]
+    )
+}
+
+/// set_srcinfo_values replaces the "key = value" lines of .SRCINFO with one line per value, at the
+/// place of the first of them. Nothing changes when there is no such line.
+fn set_srcinfo_values(srcinfo: &str, key: &str, values: &[String]) -> String {
+    let prefix = format!("\t{} = ", key);
+    let mut replaced = false;
+    let mut result = String::new();
+
+    for line in srcinfo.split_inclusive('\n') {
+        if !line.starts_with(&prefix) {
+            result.push_str(line);
+            continue;
+        }
+
+        if !replaced {
+            for value in values {
+                result.push_str(&format!("{}{}\n", prefix, value));
+            }
+            replaced = true;
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::checksum::compute_checksum;
+    use crate::pkgbuild::generate_pkgbuild;
+    use crate::shared::tests::information;
+    use crate::srcinfo::generate_srcinfo;
+    use crate::templates::Templates;
+
+    #[test]
+    fn assignment_is_set_only_at_top_level() {
+        let pkgbuild = "pkgver=1.0.0\npkgrel=2\n_pkgver=1.0.0\n";
+
+        assert_eq!(
+            set_assignment(pkgbuild, "pkgver", "1.1.0"),
+            "pkgver=1.1.0\npkgrel=2\n_pkgver=1.0.0\n"
+        );
+    }
+
+    #[test]
+    fn array_values_are_replaced() {
+        let pkgbuild = "source=(\"foo-$pkgver.tar.gz\")\nsha256sums=('aaa')\nb2sums=('bbb')\n";
+
+        assert_eq!(
+            set_array(pkgbuild, "sha256sums", &["ccc".to_string()]),
+            "source=(\"foo-$pkgver.tar.gz\")\nsha256sums=('ccc')\nb2sums=('bbb')\n"
+        );
+        assert_eq!(
+            set_array(pkgbuild, "sha512sums", &["ccc".to_string()]),
+            pkgbuild
+        );
+    }
+
+    #[test]
+    fn srcinfo_values_take_the_place_of_the_first() {
+        let srcinfo = "pkgbase = foo\n\tsource = a\n\tsource = b\n\tarch = any\n";
+
+        assert_eq!(
+            set_srcinfo_values(srcinfo, "source", &["c".to_string()]),
+            "pkgbase = foo\n\tsource = c\n\tarch = any\n"
+        );
+        assert_eq!(set_srcinfo_values(srcinfo, "depends", &[]), srcinfo);
+    }
+
+    #[test]
+    fn bump_keeps_pkgbuild_and_srcinfo_in_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let pkginfo = information();
+        let templates = Templates::default();
+        fs::write(
+            dir.path().join("PKGBUILD"),
+            generate_pkgbuild(&pkginfo, &templates).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join(".SRCINFO"),
+            generate_srcinfo(&pkginfo, &templates).unwrap(),
+        )
+        .unwrap();
+        let tarball = dir.path().join("foo-2.0.0.tar.gz");
+        fs::write(&tarball, "foo 2.0.0").unwrap();
+        let fetch = FetchOptions {
+            retries: 0,
+            timeout: Duration::from_secs(1),
+            offline: true,
+        };
+
+        bump_package(&Bump::Version("2.0.0".to_string()), dir.path(), &fetch).unwrap();
+
+        let pkgbuild = fs::read_to_string(dir.path().join("PKGBUILD")).unwrap();
+        let srcinfo = fs::read_to_string(dir.path().join(".SRCINFO")).unwrap();
+        let checksum =
+            compute_checksum(&tarball.display().to_string(), &ChecksumAlgorithm::Sha256).unwrap();
+        assert!(pkgbuild.contains("\npkgver=2.0.0\n"));
+        assert!(pkgbuild.contains("\npkgrel=1\n"));
+        assert_eq!(
+            parse_array(&pkgbuild, "sha256sums"),
+            Some(vec![checksum.clone()])
+        );
+        assert!(srcinfo.contains("\tpkgver = 2.0.0\n"));
+        assert!(srcinfo.contains("\tpkgrel = 1\n"));
+        assert!(srcinfo.contains("\tsource = foo-2.0.0.tar.gz\n"));
+        assert!(srcinfo.contains(&format!("\tsha256sums = {}\n", checksum)));
+    }
+}
@@ -0,0 +1,399 @@
+//! checksum module handles the checksums of sources
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+use blake2::Blake2b512;
+use log::info;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use sha256::try_digest;
+
+use crate::error::AurdersError;
+use crate::shared::Information;
+use crate::tui;
+use crate::utils::{fail_in_batch, fetch_data, print_prompt, read_input, tui, FetchOptions};
+
+/// ChecksumAlgorithm is the algorithm used to generate the checksums of sources
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    #[default]
+    Sha256,
+    Sha512,
+    B2,
+}
+
+impl ChecksumAlgorithm {
+    /// from_name returns the algorithm of given name (sha256, sha512 or b2)
+    pub fn from_name(name: &str) -> Option<ChecksumAlgorithm> {
+        match name {
+            "sha256" => Some(ChecksumAlgorithm::Sha256),
+            "sha512" => Some(ChecksumAlgorithm::Sha512),
+            "b2" => Some(ChecksumAlgorithm::B2),
+            _ => None,
+        }
+    }
+
+    /// array returns the name of PKGBUILD array holding the checksums of this algorithm
+    pub fn array(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256sums",
+            ChecksumAlgorithm::Sha512 => "sha512sums",
+            ChecksumAlgorithm::B2 => "b2sums",
+        }
+    }
+}
+
+/// select_checksum allows user to choose the checksum algorithm, sha256 being the default
+pub fn select_checksum() -> Result<ChecksumAlgorithm, AurdersError> {
+    if tui() {
+        return tui::select_checksum();
+    }
+
+    print_prompt("\nSelect the checksum algorithm for sources:\n");
+
+    loop {
+        print_prompt("  [1] sha256(Default)    [2] sha512    [3] b2\n> ");
+        io::stdout().flush().unwrap();
+        let mut input = String::new();
+
+        read_input(&mut input)?;
+
+        // sha256 as default algorithm
+        let algorithm: u8 = input.trim().parse().unwrap_or(1);
+
+        match algorithm {
+            1 => return Ok(ChecksumAlgorithm::Sha256),
+            2 => return Ok(ChecksumAlgorithm::Sha512),
+            3 => return Ok(ChecksumAlgorithm::B2),
+            _ => {
+                eprintln!("Invalid input. Try again");
+                fail_in_batch()?;
+            }
+        };
+    }
+}
+
+/// compute_checksum generates the digest of tarball with given algorithm and returns it
+pub fn compute_checksum(tarball: &String, algorithm: &ChecksumAlgorithm) -> Option<String> {
+    let input = Path::new(&tarball);
+    let value_result = match algorithm {
+        ChecksumAlgorithm::Sha256 => try_digest(input),
+        ChecksumAlgorithm::Sha512 => digest_file::<Sha512>(input),
+        ChecksumAlgorithm::B2 => digest_file::<Blake2b512>(input),
+    };
+
+    match value_result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!(
+                "Failed to get {}: {}.\nUsing 'SKIP' as default value.",
+                algorithm.array(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// source_checksums computes the checksum of every source of pkginfo, in the same order, without
+/// asking user for any of them. Sources are looked up by the name their variables expand to.
+/// Local sources are hashed from directory, where makepkg expects them, and are copied there
+/// first (if copy is set) when given as a path elsewhere. Remote sources are downloaded to be
+/// hashed as fetch says, VCS sources and anything that cannot be fetched get 'SKIP'.
+pub fn source_checksums(
+    pkginfo: &Information,
+    directory: &Path,
+    copy: bool,
+    fetch: &FetchOptions,
+) -> Vec<String> {
+    checksums_of(&pkginfo.source, pkginfo, directory, copy, fetch)
+}
+
+/// arch_source_checksums computes the checksums of the sources specific to each architecture of
+/// pkginfo, in the same order, the same way as source_checksums
+pub fn arch_source_checksums(
+    pkginfo: &Information,
+    directory: &Path,
+    copy: bool,
+    fetch: &FetchOptions,
+) -> Vec<Vec<String>> {
+    pkginfo
+        .arch_source
+        .iter()
+        .map(|arch_source| checksums_of(&arch_source.source, pkginfo, directory, copy, fetch))
+        .collect()
+}
+
+/// checksums_of computes the checksum of every source of sources, see source_checksums
+fn checksums_of(
+    sources: &[String],
+    pkginfo: &Information,
+    directory: &Path,
+    copy: bool,
+    fetch: &FetchOptions,
+) -> Vec<String> {
+    let algorithm = &pkginfo.checksum_algorithm;
+
+    sources
+        .iter()
+        .map(|source| {
+            // makepkg looks for the file the source names once its variables are expanded
+            let source = &pkginfo.expand_variables(source);
+
+            if is_vcs(source) {
+                return "SKIP".to_string();
+            }
+
+            if is_remote(source) {
+                return remote_checksum(source, algorithm, fetch);
+            }
+
+            let file = match local_source(source, directory, copy) {
+                Some(file) => file,
+                None => {
+                    info!("{} is not available locally, using 'SKIP'.", source);
+                    return "SKIP".to_string();
+                }
+            };
+
+            match compute_checksum(&file, algorithm) {
+                Some(checksum) => checksum,
+                None => "SKIP".to_string(),
+            }
+        })
+        .collect()
+}
+
+/// remote_checksum downloads remote source to a temporary file and returns its checksum, falling
+/// back to 'SKIP' when it cannot be downloaded
+fn remote_checksum(source: &str, algorithm: &ChecksumAlgorithm, fetch: &FetchOptions) -> String {
+    let url = match source.split_once("::") {
+        Some((_, url)) => url,
+        None => source,
+    };
+    let file = env::temp_dir()
+        .join(format!("aurders-{}", source_filename(source)))
+        .display()
+        .to_string();
+
+    let checksum = match fetch_data(url.to_string(), file.clone(), fetch) {
+        Ok(_) => compute_checksum(&file, algorithm),
+        Err(e) => {
+            eprintln!("Failed to download {}: {}.\nUsing 'SKIP' instead.", url, e);
+            None
+        }
+    };
+
+    // leftover of a failed download is of no use either
+    let _ = fs::remove_file(&file);
+
+    match checksum {
+        Some(checksum) => checksum,
+        None => "SKIP".to_string(),
+    }
+}
+
+/// local_source finds the file of a local source inside directory, copying it there when source
+/// is a path to an existing file elsewhere and copy is allowed. Returns the path of file found.
+fn local_source(source: &str, directory: &Path, copy: bool) -> Option<String> {
+    let file = directory.join(source_filename(source));
+
+    if file.is_file() {
+        return Some(file.display().to_string());
+    }
+
+    let given = Path::new(source);
+
+    if !given.is_file() {
+        return None;
+    }
+
+    if !copy {
+        return Some(source.to_string());
+    }
+
+    match fs::copy(given, &file) {
+        Ok(_) => {
+            info!("Copied {} to {}.", source, directory.display());
+            Some(file.display().to_string())
+        }
+        Err(e) => {
+            eprintln!("Failed to copy {}: {}.", source, e);
+            None
+        }
+    }
+}
+
+/// pair_checksums returns one checksum for every source, in the same order, SKIP standing for VCS
+/// sources and sources whose checksum is missing, as makepkg pairs them by position
+pub fn pair_checksums(sources: &[String], checksums: &[String]) -> Vec<String> {
+    sources
+        .iter()
+        .enumerate()
+        .map(|(index, source)| match checksums.get(index) {
+            Some(checksum) if !is_vcs(source) && !checksum.is_empty() => checksum.clone(),
+            _ => "SKIP".to_string(),
+        })
+        .collect()
+}
+
+/// expand_source_dirs replaces every local source that is a directory with the files directly
+/// inside it, hidden ones left out, sorted by name. Other sources are kept as they are.
+pub fn expand_source_dirs(sources: Vec<String>) -> Result<Vec<String>, AurdersError> {
+    let mut expanded = Vec::new();
+
+    for source in sources {
+        let dir = Path::new(&source);
+
+        if is_remote(&source) || is_vcs(&source) || !dir.is_dir() {
+            expanded.push(source);
+            continue;
+        }
+
+        let entries = fs::read_dir(dir).map_err(|e| {
+            AurdersError::Failed(format!("Failed to read source directory {}: {}", source, e))
+        })?;
+
+        let mut files: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .map(|path| path.display().to_string())
+            .collect();
+        files.sort();
+
+        if files.is_empty() {
+            eprintln!(
+                "Warning: source directory {} has no files, leaving it out.",
+                source
+            );
+        } else {
+            info!(
+                "Collected {} file(s) of {} as sources.",
+                files.len(),
+                source
+            );
+        }
+
+        expanded.extend(files);
+    }
+
+    Ok(expanded)
+}
+
+/// is_vcs tells whether source is cloned from a version control system, which has no checksum
+pub fn is_vcs(source: &str) -> bool {
+    let location = match source.split_once("::") {
+        Some((_, location)) => location,
+        None => source,
+    };
+
+    ["git+", "svn+", "hg+", "bzr+", "fossil+"]
+        .iter()
+        .any(|prefix| location.starts_with(prefix))
+}
+
+/// is_remote tells whether source is downloaded by makepkg rather than provided locally
+pub fn is_remote(source: &str) -> bool {
+    let location = match source.split_once("::") {
+        Some((_, location)) => location,
+        None => source,
+    };
+
+    location.contains("://")
+}
+
+/// source_filename returns the name of file that makepkg saves source as, which is either the
+/// name given before "::" or the last component of path/url
+pub fn source_filename(source: &str) -> String {
+    match source.split_once("::") {
+        Some((name, _)) => name.to_string(),
+        None => source
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or(source)
+            .to_string(),
+    }
+}
+
+/// digest_file is a helper function to hash the file at path with hasher D, returning hex digest
+fn digest_file<D: Digest + Write>(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = D::new();
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renamed_source_is_saved_under_its_name() {
+        assert_eq!(
+            source_filename("foo-1.0.tar.gz::https://example.com/download?id=1"),
+            "foo-1.0.tar.gz"
+        );
+        assert!(is_remote(
+            "foo-1.0.tar.gz::https://example.com/download?id=1"
+        ));
+    }
+
+    #[test]
+    fn checksum_of_known_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("hello");
+        fs::write(&file, "hello\n").unwrap();
+        let file = file.display().to_string();
+
+        assert_eq!(
+            compute_checksum(&file, &ChecksumAlgorithm::Sha256).as_deref(),
+            Some("5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03")
+        );
+        assert_eq!(
+            compute_checksum(&file, &ChecksumAlgorithm::Sha512).as_deref(),
+            Some("e7c22b994c59d9cf2b48e549b1e24666636045930d3da7c1acb299d1c3b7f931f94aae41edda2c2b207a36e10f8bcb8d45223e54878f5b316e7ce3b6bc019629")
+        );
+        assert_eq!(
+            compute_checksum(&file, &ChecksumAlgorithm::B2).as_deref(),
+            Some("f60ce482e5cc1229f39d71313171a8d9f4ca3a87d066bf4b205effb528192a75f14f3271e2c1a90e1de53f275b4d4793eef2f5e31ea90d2ce29d2e481c36435f")
+        );
+    }
+
+    #[test]
+    fn source_dir_expands_to_its_visible_files_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let patches = dir.path().join("patches");
+        fs::create_dir_all(patches.join("nested")).unwrap();
+        for name in ["b.patch", "a.patch", ".hidden.patch", "c.service"] {
+            fs::write(patches.join(name), name).unwrap();
+        }
+        let patches = patches.display().to_string();
+
+        let expanded = expand_source_dirs(vec![
+            "https://example.com/foo-$pkgver.tar.gz".to_string(),
+            patches.clone(),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            expanded,
+            [
+                "https://example.com/foo-$pkgver.tar.gz".to_string(),
+                format!("{}/a.patch", patches),
+                format!("{}/b.patch", patches),
+                format!("{}/c.service", patches),
+            ]
+        );
+    }
+}
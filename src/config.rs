@@ -0,0 +1,231 @@
+//! config module handles reading and writing the package information from/to a file, which lets
+//! aurders run without prompting
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::build_system::BuildSystem;
+use crate::checksum::ChecksumAlgorithm;
+use crate::shared::Information;
+
+/// Config stores the package information read from a file or given as flags, any field missing
+/// from it is prompted for instead
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    pub maintainer_name: Option<String>,
+    pub maintainer_email: Option<String>,
+    pub pkgbase: Option<String>,
+    pub pkgname: Option<Vec<String>>,
+    pub pkgver: Option<String>,
+    pub pkgrel: Option<String>,
+    pub epoch: Option<String>,
+    pub pkgdesc: Option<String>,
+    pub url: Option<String>,
+    pub license: Option<String>,
+    pub arch: Option<Vec<String>>,
+    pub depends: Option<String>,
+    pub makedepends: Option<String>,
+    pub checkdepends: Option<String>,
+    pub optdepends: Option<String>,
+    pub provides: Option<String>,
+    pub conflicts: Option<String>,
+    pub replaces: Option<String>,
+    pub groups: Option<String>,
+    pub install: Option<String>,
+    pub changelog: Option<String>,
+    pub backup: Option<String>,
+    pub options: Option<String>,
+    pub vcs: Option<bool>,
+    /// whether package provides a systemd service, scaffolded as one of sources
+    pub service: Option<bool>,
+    pub source: Option<Vec<String>>,
+    pub noextract: Option<String>,
+    pub validpgpkeys: Option<String>,
+    /// sources specific to architectures, by the architecture
+    pub arch_source: Option<BTreeMap<String, Vec<String>>>,
+    pub checksum_algorithm: Option<ChecksumAlgorithm>,
+    pub build_system: Option<BuildSystem>,
+    pub prepare: Option<String>,
+    pub build: Option<String>,
+    pub package: Option<Vec<String>>,
+}
+
+impl Config {
+    /// merge returns the config with fields of other taking precedence over own
+    pub fn merge(self, other: Config) -> Config {
+        Config {
+            maintainer_name: other.maintainer_name.or(self.maintainer_name),
+            maintainer_email: other.maintainer_email.or(self.maintainer_email),
+            pkgbase: other.pkgbase.or(self.pkgbase),
+            pkgname: other.pkgname.or(self.pkgname),
+            pkgver: other.pkgver.or(self.pkgver),
+            pkgrel: other.pkgrel.or(self.pkgrel),
+            epoch: other.epoch.or(self.epoch),
+            pkgdesc: other.pkgdesc.or(self.pkgdesc),
+            url: other.url.or(self.url),
+            license: other.license.or(self.license),
+            arch: other.arch.or(self.arch),
+            depends: other.depends.or(self.depends),
+            makedepends: other.makedepends.or(self.makedepends),
+            checkdepends: other.checkdepends.or(self.checkdepends),
+            optdepends: other.optdepends.or(self.optdepends),
+            provides: other.provides.or(self.provides),
+            conflicts: other.conflicts.or(self.conflicts),
+            replaces: other.replaces.or(self.replaces),
+            groups: other.groups.or(self.groups),
+            install: other.install.or(self.install),
+            changelog: other.changelog.or(self.changelog),
+            backup: other.backup.or(self.backup),
+            options: other.options.or(self.options),
+            vcs: other.vcs.or(self.vcs),
+            service: other.service.or(self.service),
+            source: other.source.or(self.source),
+            noextract: other.noextract.or(self.noextract),
+            validpgpkeys: other.validpgpkeys.or(self.validpgpkeys),
+            arch_source: other.arch_source.or(self.arch_source),
+            checksum_algorithm: other.checksum_algorithm.or(self.checksum_algorithm),
+            build_system: other.build_system.or(self.build_system),
+            prepare: other.prepare.or(self.prepare),
+            build: other.build.or(self.build),
+            package: other.package.or(self.package),
+        }
+    }
+
+    /// from_information returns the config holding every answer of pkginfo
+    pub fn from_information(pkginfo: &Information) -> Config {
+        Config {
+            maintainer_name: Some(pkginfo.maintainer_name.clone()),
+            maintainer_email: Some(pkginfo.maintainer_email.clone()),
+            pkgbase: Some(pkginfo.pkgbase.clone()),
+            pkgname: Some(pkginfo.pkgname.clone()),
+            pkgver: Some(pkginfo.pkgver.clone()),
+            pkgrel: Some(pkginfo.pkgrel.clone()),
+            epoch: Some(pkginfo.epoch.clone()),
+            pkgdesc: Some(pkginfo.pkgdesc.clone()),
+            url: Some(pkginfo.url.clone()),
+            license: Some(pkginfo.license.clone()),
+            arch: Some(pkginfo.arch.clone()),
+            depends: Some(pkginfo.depends.clone()),
+            makedepends: Some(pkginfo.makedepends.clone()),
+            checkdepends: Some(pkginfo.checkdepends.clone()),
+            optdepends: Some(pkginfo.optdepends.clone()),
+            provides: Some(pkginfo.provides.clone()),
+            conflicts: Some(pkginfo.conflicts.clone()),
+            replaces: Some(pkginfo.replaces.clone()),
+            groups: Some(pkginfo.groups.clone()),
+            install: Some(pkginfo.install.clone()),
+            changelog: Some(pkginfo.changelog.clone()),
+            backup: Some(pkginfo.backup.clone()),
+            options: Some(pkginfo.options.clone()),
+            vcs: Some(pkginfo.vcs),
+            service: Some(
+                pkginfo
+                    .source
+                    .iter()
+                    .any(|source| source.ends_with(".service")),
+            ),
+            source: Some(pkginfo.source.clone()),
+            noextract: Some(pkginfo.noextract.clone()),
+            validpgpkeys: Some(pkginfo.validpgpkeys.clone()),
+            arch_source: Some(
+                pkginfo
+                    .arch_source
+                    .iter()
+                    .map(|arch_source| (arch_source.arch.clone(), arch_source.source.clone()))
+                    .collect(),
+            ),
+            checksum_algorithm: Some(pkginfo.checksum_algorithm.clone()),
+            build_system: Some(pkginfo.build_system.clone()),
+            prepare: Some(pkginfo.prepare.clone()),
+            build: Some(pkginfo.build.clone()),
+            package: Some(pkginfo.package.clone()),
+        }
+    }
+}
+
+/// is_json tells whether path is a JSON file, anything else is treated as TOML
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+/// read_config reads and returns the config from TOML or JSON file at path
+pub fn read_config(path: &Path) -> Result<Config, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+
+    if is_json(path) {
+        Ok(serde_json::from_str(&contents)?)
+    } else {
+        Ok(toml::from_str(&contents)?)
+    }
+}
+
+/// write_config writes the answers of pkginfo to TOML or JSON file at path
+pub fn write_config(path: &Path, pkginfo: &Information) -> Result<(), Box<dyn Error>> {
+    let config = Config::from_information(pkginfo);
+
+    let contents = if is_json(path) {
+        serde_json::to_string_pretty(&config)?
+    } else {
+        toml::to_string_pretty(&config)?
+    };
+
+    fs::write(path, contents)?;
+
+    Ok(())
+}
+
+/// UserConfig stores what aurders remembers of its user across runs, in config.toml under the
+/// aurders directory of XDG config home
+#[derive(Default, Serialize, Deserialize)]
+pub struct UserConfig {
+    pub maintainer_name: Option<String>,
+    pub maintainer_email: Option<String>,
+}
+
+/// user_config_path returns the path of user config, $XDG_CONFIG_HOME/aurders/config.toml or
+/// ~/.config/aurders/config.toml when XDG_CONFIG_HOME is not set. None if neither can be found.
+pub fn user_config_path() -> Option<PathBuf> {
+    // relative XDG_CONFIG_HOME is invalid as per the spec, and is to be ignored
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_home.join("aurders").join("config.toml"))
+}
+
+/// read_user_config reads the user config, which is empty when it does not exist yet
+pub fn read_user_config() -> UserConfig {
+    let path = match user_config_path() {
+        Some(path) => path,
+        None => return UserConfig::default(),
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => match toml::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Ignoring invalid config {}: {}.", path.display(), e);
+                UserConfig::default()
+            }
+        },
+        Err(_) => UserConfig::default(),
+    }
+}
+
+/// write_user_config writes config as the user config, creating its directory if needed
+pub fn write_user_config(config: &UserConfig) -> Result<PathBuf, Box<dyn Error>> {
+    let path = user_config_path().ok_or("neither XDG_CONFIG_HOME nor HOME is set")?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, toml::to_string_pretty(config)?)?;
+
+    Ok(path)
+}
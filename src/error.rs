@@ -0,0 +1,52 @@
+//! error module holds the errors helpers report back instead of exiting, leaving the decision to
+//! their caller
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// AurdersError is the error of a step aurders could not complete
+#[derive(Debug)]
+pub enum AurdersError {
+    /// reading or writing a file failed
+    Io(io::Error),
+    /// user input could not be read
+    Input(io::Error),
+    /// source directory cannot be packed into a tarball
+    Source(String),
+    /// architecture of this machine is not one Arch Linux runs on
+    UnsupportedArch(String),
+    /// input ended while an answer was still needed
+    NoInput,
+    /// answer in batch input is invalid, asking again would shift every answer after it to the
+    /// wrong prompt
+    InvalidAnswer,
+    /// user chose not to go on
+    Aborted,
+    /// any other step that failed, described by the message
+    Failed(String),
+}
+
+impl fmt::Display for AurdersError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AurdersError::Io(e) => write!(f, "{}", e),
+            AurdersError::Input(e) => write!(f, "Unable to take input: {}", e),
+            AurdersError::Source(message) => write!(f, "{}", message),
+            AurdersError::UnsupportedArch(arch) => {
+                write!(f, "Architecture {} is not supported by Arch Linux", arch)
+            }
+            AurdersError::NoInput => write!(f, "No more input"),
+            AurdersError::InvalidAnswer => write!(f, "Invalid answer in batch input"),
+            AurdersError::Aborted => write!(f, "Aborted"),
+            AurdersError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for AurdersError {}
+
+impl From<io::Error> for AurdersError {
+    fn from(e: io::Error) -> AurdersError {
+        AurdersError::Io(e)
+    }
+}
@@ -1,52 +1,177 @@
 //! I (mitesh) could not think of any name for this module. And therefore, final_step exists. I'll
 //! think of something ASAP and change this, if I remember to do so.
+use std::error::Error;
 use std::io::{self, BufRead};
+use std::path::Path;
 use std::process::Command;
 use std::{env, fs};
 
-use crate::utils::{dead, get_arch};
+use log::info;
 
-/// execute_makepkg executes the makepkg command
-pub fn execute_makepkg() {
-    match env::set_current_dir(format!("aurders")) {
+use crate::error::AurdersError;
+use crate::utils::{dead_probably, find_program, get_arch, input_bool, print_prompt, unified_diff};
+
+/// execute_makepkg executes the makepkg command inside output_dir
+pub fn execute_makepkg(output_dir: &Path) -> Result<(), AurdersError> {
+    match env::set_current_dir(output_dir) {
         Ok(_) => (),
         Err(e) => eprintln!("Failed to change current directory: {}.", e),
     };
 
-    println!("\nExecuting makepkg...");
+    info!("\nExecuting makepkg...");
     let output = Command::new("makepkg").output();
 
     match output {
         Ok(op) => {
             if op.status.success() {
-                println!("Executed makepkg successfullly.");
+                info!("Executed makepkg successfullly.");
             } else {
                 if let Ok(stderr) = String::from_utf8(op.stderr) {
-                    eprintln!("makepkg failed: {}.", stderr);
-                    dead();
+                    return Err(AurdersError::Failed(format!("makepkg failed: {}", stderr)));
                 } else {
                     eprintln!("Failed to read stderr.");
                 }
             }
         }
+        Err(e) => return Err(AurdersError::Failed(format!("makepkg failed: {}", e))),
+    };
+
+    Ok(())
+}
+
+/// print_srcinfo runs makepkg --printsrcinfo inside output_dir and returns the .SRCINFO it prints
+/// for the PKGBUILD there
+pub fn print_srcinfo(output_dir: &Path) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("makepkg")
+        .arg("--printsrcinfo")
+        .current_dir(output_dir)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().into());
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// check_srcinfo compares .SRCINFO inside output_dir with the one makepkg prints for the PKGBUILD
+/// there, printing the difference as a unified diff. Returns whether they match, and an error when
+/// they cannot be compared at all (e.g. makepkg is not installed).
+pub fn check_srcinfo(output_dir: &Path) -> Result<bool, AurdersError> {
+    if find_program("makepkg").is_none() {
+        return Err(AurdersError::Failed(
+            "makepkg is not installed, .SRCINFO cannot be checked without it".to_string(),
+        ));
+    }
+
+    let path = output_dir.join(".SRCINFO");
+    let committed = fs::read_to_string(&path)
+        .map_err(|e| AurdersError::Failed(format!("Failed to read {}: {}", path.display(), e)))?;
+
+    let expected = print_srcinfo(output_dir)
+        .map_err(|e| AurdersError::Failed(format!("makepkg --printsrcinfo failed: {}", e)))?;
+
+    if committed == expected {
+        info!(".SRCINFO is up to date with PKGBUILD.");
+        return Ok(true);
+    }
+
+    eprintln!("Regenerate .SRCINFO with makepkg --printsrcinfo > .SRCINFO, it differs as follows:");
+    print!(
+        "{}",
+        unified_diff(&committed, &expected, "a/.SRCINFO", "b/.SRCINFO")
+    );
+
+    Ok(false)
+}
+
+/// verify_pkgbuild checks the PKGBUILD inside dir by having makepkg parse it, printing whatever
+/// makepkg complains about. Returns whether makepkg accepted it.
+pub fn verify_pkgbuild(dir: &Path) -> bool {
+    info!("\nVerifying PKGBUILD with makepkg --printsrcinfo...");
+
+    let output = Command::new("makepkg")
+        .arg("--printsrcinfo")
+        .current_dir(dir)
+        .output();
+
+    match output {
+        Ok(op) => {
+            for line in String::from_utf8_lossy(&op.stderr).lines() {
+                eprintln!("makepkg: {}", line);
+            }
+
+            if op.status.success() {
+                info!("PKGBUILD is valid.");
+            } else {
+                eprintln!("PKGBUILD is invalid.");
+            }
+            op.status.success()
+        }
+        Err(e) => {
+            eprintln!("Failed to run makepkg: {}.", e);
+            false
+        }
+    }
+}
+
+/// lint_pkgbuild runs namcap on the PKGBUILD inside dir, listing its errors before its warnings.
+/// Returns whether namcap found no errors.
+pub fn lint_pkgbuild(dir: &Path) -> bool {
+    info!("\nLinting PKGBUILD with namcap...");
+
+    let output = Command::new("namcap")
+        .arg("PKGBUILD")
+        .current_dir(dir)
+        .output();
+
+    let stdout = match output {
+        Ok(op) => String::from_utf8_lossy(&op.stdout).to_string(),
         Err(e) => {
-            eprintln!("makepkg failed: {}.", e);
-            dead();
+            eprintln!("Failed to run namcap: {}.", e);
+            return false;
         }
     };
+
+    // every line is in the form of "PKGBUILD (pkgname) E: message", W being a warning
+    let mut errors: Vec<&str> = Vec::new();
+    let mut warnings: Vec<&str> = Vec::new();
+
+    for line in stdout.lines() {
+        if let Some((_, message)) = line.split_once(" E: ") {
+            errors.push(message);
+        } else if let Some((_, message)) = line.split_once(" W: ") {
+            warnings.push(message);
+        }
+    }
+
+    for error in &errors {
+        eprintln!("  error: {}", error);
+    }
+    for warning in &warnings {
+        eprintln!("  warning: {}", warning);
+    }
+
+    info!(
+        "namcap found {} error(s) and {} warning(s).",
+        errors.len(),
+        warnings.len()
+    );
+
+    errors.is_empty()
 }
 
-/// clone_aur_repo clones the repository of pkgname from aur.archlinux.org
-fn clone_aur_repo(pkgname: &String) -> Option<()> {
+/// clone_aur_repo clones the repository of pkgbase from aur.archlinux.org
+fn clone_aur_repo(pkgbase: &String) -> Option<()> {
     // requires aur.archlinux.org to be in known_hosts (probably?)
-    let clone_url = format!("ssh://aur@aur.archlinux.org/{}.git", &pkgname);
+    let clone_url = format!("ssh://aur@aur.archlinux.org/{}.git", &pkgbase);
 
     let output = Command::new("git").arg("clone").arg(clone_url).output();
 
     match output {
         Ok(op) => {
             if op.status.success() {
-                println!("Cloned repository successfully.");
+                info!("Cloned repository successfully.");
                 return Some(());
             } else {
                 if let Ok(stderr) = String::from_utf8(op.stderr) {
@@ -65,12 +190,12 @@ fn clone_aur_repo(pkgname: &String) -> Option<()> {
 }
 
 /// add_to_repo adds and commits the files to aur@aur.archlinux.org repository
-pub fn add_to_repo(pkgname: &String) {
-    match env::set_current_dir(pkgname) {
+pub fn add_to_repo(pkgbase: &String) -> Result<(), AurdersError> {
+    match env::set_current_dir(pkgbase) {
         Ok(_) => (),
         Err(e) => {
             eprintln!("Failed to change current directory: {}.", e);
-            return;
+            return Ok(());
         }
     };
 
@@ -79,65 +204,197 @@ pub fn add_to_repo(pkgname: &String) {
     match output {
         Ok(op) => {
             if op.status.success() {
-                println!("Added files to git.");
+                info!("Added files to git.");
             } else {
                 if let Ok(stderr) = String::from_utf8(op.stderr) {
-                    eprintln!("git add failed: {}.", stderr);
-                    dead();
+                    return Err(AurdersError::Failed(format!("git add failed: {}", stderr)));
                 } else {
                     eprintln!("Failed to read stderr.");
                 }
             }
         }
-        Err(e) => {
-            eprintln!("git add failed: {}.", e);
-            dead();
-        }
+        Err(e) => return Err(AurdersError::Failed(format!("git add failed: {}", e))),
     };
+
+    Ok(())
 }
 
-/// setup_repo sets up the repository to publish
-pub fn setup_repo(pkgname: &String, pkgver: &String, pkgrel: &String, architecture: &String) {
-    println!("\nSetting up git repository...");
+/// setup_repo sets up the repository to publish, copying PKGBUILD, .SRCINFO, files and the built
+/// packages into the clone of AUR repository
+pub fn setup_repo(
+    pkgbase: &String,
+    pkgname: &Vec<String>,
+    pkgver: &String,
+    pkgrel: &String,
+    architecture: &[String],
+    files: &[String],
+) -> Result<(), AurdersError> {
+    info!("\nSetting up git repository...");
 
-    match clone_aur_repo(&pkgname) {
+    match clone_aur_repo(&pkgbase) {
         Some(_) => (),
-        None => return,
+        None => return Ok(()),
     }
 
-    match fs::copy("PKGBUILD", format!("{}/PKGBUILD", &pkgname)) {
-        Ok(_) => println!("\nCopied PKGBUILD."),
+    match fs::copy("PKGBUILD", format!("{}/PKGBUILD", &pkgbase)) {
+        Ok(_) => info!("\nCopied PKGBUILD."),
         Err(e) => eprintln!("Failed to copy PKGBUILD: {}.", e),
     };
 
-    match fs::copy(".SRCINFO", format!("{}/.SRCINFO", &pkgname)) {
-        Ok(_) => println!("Copied .SRCINFO."),
+    match fs::copy(".SRCINFO", format!("{}/.SRCINFO", &pkgbase)) {
+        Ok(_) => info!("Copied .SRCINFO."),
         Err(e) => eprintln!("Failed to copy .SRCINFO: {}.", e),
     };
 
-    let mut arch = get_arch();
+    for file in files {
+        match fs::copy(file, format!("{}/{}", &pkgbase, file)) {
+            Ok(_) => info!("Copied {}.", file),
+            Err(e) => eprintln!("Failed to copy {}: {}.", file, e),
+        };
+    }
+
+    let mut arch = match get_arch() {
+        Ok(arch) => arch,
+        Err(e) => {
+            eprintln!("{}.", e);
+            eprintln!("You might want to modify the file name of package (.pkg.tar.zst).");
+            dead_probably()?;
+            "UNSUPPORTED".to_string()
+        }
+    };
 
-    if *architecture == "any".to_string() {
+    if architecture.contains(&"any".to_string()) {
         arch = "any".to_string();
     }
 
-    match fs::copy(
-        format!("{}-{}-{}-{}.pkg.tar.zst", &pkgname, &pkgver, &pkgrel, &arch),
-        format!(
-            "{}/{}-{}-{}-{}.pkg.tar.zst",
-            &pkgname, &pkgname, &pkgver, &pkgrel, &arch
-        ),
-    ) {         // what is this formattttiinnggn???? @cargo_fmt
-        Ok(_) => println!("Copied package.\n"),
+    // makepkg builds one package for each of the split packages
+    for name in pkgname {
+        let package = format!("{}-{}-{}-{}.pkg.tar.zst", &name, &pkgver, &pkgrel, &arch);
+
+        fs::copy(&package, format!("{}/{}", &pkgbase, &package))
+            .map_err(|e| AurdersError::Failed(format!("Failed to copy package: {}", e)))?;
+        info!("Copied package {}.", &package);
+    }
+
+    println!();
+
+    Ok(())
+}
+
+/// git runs git with args inside dir, printing what git complained about when it fails. Returns
+/// whether it succeeded.
+fn git(dir: &Path, args: &[&str]) -> bool {
+    match Command::new("git").args(args).current_dir(dir).output() {
+        Ok(op) if op.status.success() => true,
+        Ok(op) => {
+            eprintln!(
+                "git {} failed: {}.",
+                args[0],
+                String::from_utf8_lossy(&op.stderr).trim()
+            );
+            false
+        }
         Err(e) => {
-            eprintln!("Failed to copy package: {}.", e);
-            dead();
+            eprintln!("git {} failed: {}.", args[0], e);
+            false
         }
+    }
+}
+
+/// init_repo makes output_dir a git repository with the AUR repository of pkgbase as its origin,
+/// and adds files to it. Nothing is committed or pushed. Returns whether repository is ready,
+/// which it is not when git is not installed, and an error when git fails.
+pub fn init_repo(output_dir: &Path, pkgbase: &str, files: &[&str]) -> Result<bool, AurdersError> {
+    if find_program("git").is_none() {
+        info!(
+            "
+git is not installed, skipping initializing git repository."
+        );
+        return Ok(false);
+    }
+    let failed = || {
+        AurdersError::Failed(format!(
+            "Failed to initialize git repository in {}",
+            output_dir.display()
+        ))
     };
+
+    info!(
+        "
+Initializing git repository..."
+    );
+
+    // AUR only accepts pushes to master
+    if !output_dir.join(".git").exists() && !git(output_dir, &["init", "-b", "master"]) {
+        return Err(failed());
+    }
+
+    let remote = format!("ssh://aur@aur.archlinux.org/{}.git", pkgbase);
+    let has_origin = Command::new("git")
+        .args(["remote", "get-url", "origin"])
+        .current_dir(output_dir)
+        .output()
+        .is_ok_and(|op| op.status.success());
+    let set_remote = if has_origin {
+        git(output_dir, &["remote", "set-url", "origin", &remote])
+    } else {
+        git(output_dir, &["remote", "add", "origin", &remote])
+    };
+    if !set_remote {
+        return Err(failed());
+    }
+
+    let mut add = vec!["add", "--"];
+    add.extend(files);
+    if !git(output_dir, &add) {
+        return Err(failed());
+    }
+
+    info!(
+        "Initialized git repository in {} with origin {}, commit and push when ready.",
+        output_dir.display(),
+        remote
+    );
+
+    Ok(true)
+}
+
+/// publish_repo commits the files added to git repository in output_dir with message and, once
+/// user confirms, pushes them to AUR. .SRCINFO is rewritten with srcinfo first if it is missing or
+/// stale, as AUR rejects pushes without an up to date one.
+pub fn publish_repo(output_dir: &Path, srcinfo: &str, message: &str) -> Result<(), AurdersError> {
+    let path = output_dir.join(".SRCINFO");
+    if fs::read_to_string(&path).ok().as_deref() != Some(srcinfo) {
+        fs::write(&path, srcinfo).map_err(|e| {
+            AurdersError::Failed(format!("Failed to write {}: {}", path.display(), e))
+        })?;
+        info!("Regenerated stale .SRCINFO.");
+    }
+
+    if !git(output_dir, &["add", "--", ".SRCINFO"]) || !git(output_dir, &["commit", "-m", message])
+    {
+        return Err(AurdersError::Failed(format!(
+            "Failed to commit '{}'",
+            message
+        )));
+    }
+    info!("Committed '{}'.", message);
+
+    if !input_bool("Push the commit to AUR?(y/N): ")? {
+        info!("Not pushing, run 'git push origin master' when ready.");
+        return Ok(());
+    }
+
+    if !git(output_dir, &["push", "origin", "master"]) {
+        return Err(AurdersError::Failed("Failed to push to AUR".to_string()));
+    }
+    info!("Pushed to AUR successfully.");
+
+    Ok(())
 }
 
 /// commit_to_repo commits the changes in git repository
-pub fn commit_to_repo() {
+pub fn commit_to_repo() -> Result<(), AurdersError> {
     let commit_message = get_commit_message();
 
     let output = Command::new("git")
@@ -152,18 +409,19 @@ pub fn commit_to_repo() {
                 println!("\nExecuted git commit successfully.");
             } else {
                 if let Ok(stderr) = String::from_utf8(op.stderr) {
-                    eprintln!("git commit failed: {}.", stderr);
-                    dead();
+                    return Err(AurdersError::Failed(format!(
+                        "git commit failed: {}",
+                        stderr
+                    )));
                 } else {
                     eprintln!("Failed to read stderr.");
                 }
             }
         }
-        Err(e) => {
-            eprintln!("git commit failed: {}.", e);
-            dead();
-        }
+        Err(e) => return Err(AurdersError::Failed(format!("git commit failed: {}", e))),
     };
+
+    Ok(())
 }
 
 /// get_commit_message gets commit message from user and returns it
@@ -171,7 +429,7 @@ fn get_commit_message() -> String {
     let stdin = io::stdin();
     let mut message = String::new();
 
-    println!("\nEnter commit message [\"qq\" or EOF signal to quit]");
+    print_prompt("\nEnter commit message [\"qq\" or EOF signal to quit]\n");
 
     // lock the stdin and take multiline input correctly
     for line in stdin.lock().lines() {
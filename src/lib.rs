@@ -0,0 +1,23 @@
+//! aurders generates PKGBUILD and .SRCINFO of a package from its information, the interactive
+//! part of it lives in the binary
+pub mod args;
+pub mod build_system;
+pub mod bump;
+pub mod checksum;
+pub mod config;
+pub mod error;
+pub mod final_step;
+pub mod manifest;
+pub mod pkgbuild;
+pub mod preset;
+pub mod shared;
+pub mod srcinfo;
+pub mod templates;
+pub mod tui;
+pub mod utils;
+
+pub use error::AurdersError;
+pub use pkgbuild::generate_pkgbuild;
+pub use shared::{ArchSource, Information};
+pub use srcinfo::generate_srcinfo;
+pub use templates::Templates;
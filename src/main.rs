@@ -1,44 +1,291 @@
-mod args;
-mod final_step;
-mod pkgbuild;
-mod shared;
-mod srcinfo;
-mod utils;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::{env, fs, process};
 
-use final_step::{add_to_repo, commit_to_repo, execute_makepkg, setup_repo};
-use pkgbuild::generate_pkgbuild;
-use shared::get_information;
-use srcinfo::generate_srcinfo;
-use utils::{dead, input_bool};
+use log::info;
 
-use shared::Information;
+use aurders::args::{handle_args, Args};
+use aurders::bump::bump_package;
+use aurders::error::AurdersError;
+use aurders::final_step::{
+    add_to_repo, check_srcinfo, commit_to_repo, execute_makepkg, init_repo, lint_pkgbuild,
+    print_srcinfo, publish_repo, setup_repo, verify_pkgbuild,
+};
+use aurders::pkgbuild::{merge_pkgbuild, save_changelog, save_install_skeleton, save_pkgbuild};
+use aurders::shared::get_information;
+use aurders::srcinfo::save_srcinfo;
+use aurders::utils::{
+    dead, find_program, handle_interrupt, init_logger, input_bool, keep_created, set_batch,
+    set_keep_on_error, set_tui,
+};
+use aurders::{generate_pkgbuild, generate_srcinfo, Information, Templates};
 
 fn main() {
-    let info_result = get_information();
-    let pkginfo: Information;
+    // helpers report what went wrong, only here is it decided to exit
+    if let Err(e) = handle_args().and_then(|args| run(&args)) {
+        eprintln!("{}.", e);
+        dead();
+    }
+}
+
+/// run does everything aurders was asked to with args
+fn run(args: &Args) -> Result<(), AurdersError> {
+    init_logger(args.quiet, args.verbose);
+    set_batch(args.batch);
+    // widgets need a terminal to draw on, piped input keeps the plain prompts
+    set_tui(args.tui && io::stdin().is_terminal());
+    set_keep_on_error(args.keep_on_error);
+    handle_interrupt();
+
+    if let Some(bump) = &args.bump {
+        return bump_package(bump, &args.output_dir, &args.fetch);
+    }
+
+    if args.check {
+        if !check_srcinfo(&args.output_dir)? {
+            return Err(AurdersError::Failed(
+                ".SRCINFO is out of date with PKGBUILD".to_string(),
+            ));
+        }
+        return Ok(());
+    }
+
+    let pkginfo = get_information(args)?;
+
+    if let Some(path) = &args.emit_json {
+        emit_json(&pkginfo, path)?;
+    }
+
+    let mut templates = match &args.template_dir {
+        Some(dir) => Templates::from_dir(dir).map_err(|e| {
+            AurdersError::Failed(format!(
+                "Failed to read templates from {}: {}",
+                dir.display(),
+                e
+            ))
+        })?,
+        None => Templates::default(),
+    };
+    templates.vars = args.template_vars.clone();
+
+    let pkgbuild = generate_pkgbuild(&pkginfo, &templates).map_err(|e| {
+        AurdersError::Failed(format!("Failed to generate PKGBUILD from template: {}", e))
+    })?;
+
+    // hand-written functions of an existing PKGBUILD survive, only its fields are brought up to date
+    let existing = args.output_dir.join("PKGBUILD");
+    let merge = args.merge && existing.is_file();
+    let pkgbuild = match fs::read_to_string(&existing) {
+        Ok(contents) if merge => {
+            info!("\nMerging fields into existing {}.", existing.display());
+            merge_pkgbuild(&contents, &pkgbuild)
+        }
+        Err(e) if merge => {
+            return Err(AurdersError::Failed(format!(
+                "Failed to read {}: {}",
+                existing.display(),
+                e
+            )))
+        }
+        _ => pkgbuild,
+    };
+
+    if args.dry_run {
+        println!("\n{}", pkgbuild);
+    } else {
+        save_pkgbuild(&pkgbuild, &args.output_dir, args.force || merge)?;
+    }
+
+    // PKGBUILD refers to the scriptlet, makepkg fails unless it exists
+    let install = &pkginfo.install;
+    if !args.dry_run
+        && !install.is_empty()
+        && !args.output_dir.join(install).exists()
+        && input_bool(&format!(
+            "{} does not exist. Create a skeleton of it?(y/N): ",
+            install
+        ))?
+    {
+        save_install_skeleton(install, &args.output_dir)?;
+    }
+
+    // changelog is only shown to users, an empty one is enough to begin with
+    let changelog = &pkginfo.changelog;
+    if !args.dry_run
+        && !changelog.is_empty()
+        && !args.output_dir.join(changelog).exists()
+        && input_bool(&format!(
+            "{} does not exist. Create an empty one?(y/N): ",
+            changelog
+        ))?
+    {
+        save_changelog(changelog, &args.output_dir)?;
+    }
+
+    if args.verify {
+        match find_program("makepkg") {
+            Some(_) => check_pkgbuild(&pkgbuild, args, verify_pkgbuild)?,
+            None => info!("\nmakepkg is not installed, skipping verification."),
+        };
+    }
+
+    if args.lint {
+        match find_program("namcap") {
+            Some(_) => check_pkgbuild(&pkgbuild, args, lint_pkgbuild)?,
+            None => info!(
+                "\nnamcap is not installed, install it with 'pacman -S namcap' to lint PKGBUILD."
+            ),
+        };
+    }
 
-    match info_result {
-        Some(info) => pkginfo = info,
+    // makepkg knows best what .SRCINFO of the PKGBUILD is, template is only a fallback when it is
+    // not available or there is no PKGBUILD on disk to read
+    let printed = if !args.dry_run && find_program("makepkg").is_some() {
+        match print_srcinfo(&args.output_dir) {
+            Ok(srcinfo) => {
+                info!("\nGenerated .SRCINFO with makepkg --printsrcinfo.");
+                Some(srcinfo)
+            }
+            Err(e) => {
+                eprintln!("makepkg --printsrcinfo failed: {}.", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let srcinfo = match printed {
+        Some(srcinfo) => Ok(srcinfo),
         None => {
-            eprintln!("Failed to get information.");
-            dead();
-            return; // rust made me do this
+            info!("\nGenerating .SRCINFO from template.");
+            generate_srcinfo(&pkginfo, &templates)
         }
     };
 
-    generate_pkgbuild(&pkginfo);
-    generate_srcinfo(&pkginfo);
+    let srcinfo =
+        srcinfo.map_err(|e| AurdersError::Failed(format!("Failed to generate SRCINFO: {}", e)))?;
+
+    if args.dry_run {
+        println!("\n{}", srcinfo);
+        return Ok(());
+    }
+
+    save_srcinfo(&srcinfo, &args.output_dir, args.force || merge)?;
+    keep_created();
+
+    // AUR repository needs every file PKGBUILD refers to that makepkg does not download
+    let local_files: Vec<String> = pkginfo
+        .local_files()
+        .into_iter()
+        .filter(|file| {
+            let exists = args.output_dir.join(file).is_file();
+            if !exists {
+                eprintln!(
+                    "Warning: {} is not in {}, leaving it out of the repository.",
+                    file,
+                    args.output_dir.display()
+                );
+            }
+            exists
+        })
+        .collect();
+
+    // makepkg names the built package after the full version, including epoch
+    let version = if pkginfo.epoch.is_empty() {
+        pkginfo.pkgver.clone()
+    } else {
+        format!("{}:{}", &pkginfo.epoch, &pkginfo.pkgver)
+    };
+
+    // repository in output_dir replaces the clone of AUR repository made below
+    if args.git_init {
+        let mut files = vec!["PKGBUILD", ".SRCINFO"];
+        files.extend(local_files.iter().map(|file| file.as_str()));
+        let initialized = init_repo(&args.output_dir, &pkginfo.pkgbase, &files)?;
+
+        if initialized && args.publish {
+            let message = format!("Update to {}-{}", version, pkginfo.pkgrel);
+            publish_repo(&args.output_dir, &srcinfo, &message)?;
+        }
+        return Ok(());
+    }
 
-    execute_makepkg();
+    execute_makepkg(&args.output_dir)?;
 
-    setup_repo(&pkginfo.pkgname, &pkginfo.pkgver, &pkginfo.pkgrel, &pkginfo.arch);
-    add_to_repo(&pkginfo.pkgname);
+    setup_repo(
+        &pkginfo.pkgbase,
+        &pkginfo.pkgname,
+        &version,
+        &pkginfo.pkgrel,
+        &pkginfo.arch,
+        &local_files,
+    )?;
+    add_to_repo(&pkginfo.pkgbase)?;
 
-    let want_to_commit = input_bool("Do you want to commit changes in git repository?(y/n): ");
+    let want_to_commit = input_bool("Do you want to commit changes in git repository?(y/n): ")?;
 
     if want_to_commit {
-        commit_to_repo();
+        commit_to_repo()?;
     } else {
         println!("\nGreat! Do it your way, manually. As you wish.");
     }
+
+    Ok(())
+}
+
+/// emit_json writes pkginfo as JSON to path, or prints it when path is -
+fn emit_json(pkginfo: &Information, path: &Path) -> Result<(), AurdersError> {
+    let json = pkginfo.to_json().map_err(|e| {
+        AurdersError::Failed(format!("Failed to serialize package information: {}", e))
+    })?;
+
+    if path == Path::new("-") {
+        println!("{}", json);
+        return Ok(());
+    }
+
+    fs::write(path, json)
+        .map_err(|e| AurdersError::Failed(format!("Failed to write {}: {}", path.display(), e)))?;
+    info!("\nWrote package information to {}.", path.display());
+
+    Ok(())
+}
+
+/// check_pkgbuild runs check on the directory of pkgbuild, failing when check fails. In dry run,
+/// pkgbuild is not on disk and is checked from a temporary directory instead.
+fn check_pkgbuild(
+    pkgbuild: &str,
+    args: &Args,
+    check: fn(&Path) -> bool,
+) -> Result<(), AurdersError> {
+    let failed = || AurdersError::Failed("PKGBUILD did not pass the check".to_string());
+
+    if !args.dry_run {
+        if !check(&args.output_dir) {
+            return Err(failed());
+        }
+        return Ok(());
+    }
+
+    let dir = env::temp_dir().join(format!("aurders-check-{}", process::id()));
+
+    let passed =
+        match fs::create_dir_all(&dir).and_then(|_| fs::write(dir.join("PKGBUILD"), pkgbuild)) {
+            Ok(_) => check(&dir),
+            Err(e) => {
+                eprintln!("Failed to write PKGBUILD to check: {}.", e);
+                false
+            }
+        };
+
+    if let Err(e) = fs::remove_dir_all(&dir) {
+        eprintln!("Failed to remove {}: {}.", dir.display(), e);
+    }
+
+    if !passed {
+        return Err(failed());
+    }
+
+    Ok(())
 }
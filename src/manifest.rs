@@ -0,0 +1,109 @@
+//! manifest module reads the metadata of the project manifest (Cargo.toml, package.json) inside
+//! source directory, which the prompts start from
+use std::fs;
+use std::path::Path;
+
+use toml::Value;
+
+/// Manifest stores the package information found in the project manifest
+#[derive(Default)]
+pub struct Manifest {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+}
+
+/// read_manifest reads the metadata of Cargo.toml or package.json inside source_dir, everything is
+/// None when there is no such file or it cannot be parsed
+pub fn read_manifest(source_dir: &Path) -> Manifest {
+    read_cargo_toml(&source_dir.join("Cargo.toml"))
+        .or_else(|| read_package_json(&source_dir.join("package.json")))
+        .unwrap_or_default()
+}
+
+/// read_cargo_toml reads the version, description and repository (or homepage) of package from
+/// Cargo.toml at path
+fn read_cargo_toml(path: &Path) -> Option<Manifest> {
+    let contents = fs::read_to_string(path).ok()?;
+    let manifest = contents.parse::<Value>().ok()?;
+    let package = manifest.get("package")?;
+
+    // inherited fields ({ workspace = true }) are tables rather than strings, and are skipped
+    let field = |name: &str| {
+        package
+            .get(name)
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+    };
+
+    Some(Manifest {
+        name: None,
+        // pkgver cannot contain hyphens of pre-release versions
+        version: field("version").map(|version| version.replace('-', "_")),
+        description: field("description"),
+        url: field("repository").or_else(|| field("homepage")),
+    })
+}
+
+/// read_package_json reads the name, version, description and homepage of package from
+/// package.json at path
+fn read_package_json(path: &Path) -> Option<Manifest> {
+    let contents = fs::read_to_string(path).ok()?;
+    let package = serde_json::from_str::<serde_json::Value>(&contents).ok()?;
+
+    let field = |name: &str| {
+        package
+            .get(name)
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+    };
+
+    Some(Manifest {
+        // scope of npm packages (@scope/name) has no place in pkgname
+        name: field("name").map(|name| match name.rsplit_once('/') {
+            Some((_, name)) => name.to_lowercase(),
+            None => name.to_lowercase(),
+        }),
+        version: field("version").map(|version| version.replace('-', "_")),
+        description: field("description"),
+        url: field("homepage"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_package_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("package.json");
+        fs::write(
+            &path,
+            r#"{
+  "name": "@acme/Foo-CLI",
+  "version": "1.2.0-beta.1",
+  "description": "A foo tool",
+  "homepage": "https://example.com/foo",
+  "dependencies": { "bar": "^1.0.0" }
+}
+"#,
+        )
+        .unwrap();
+
+        let manifest = read_package_json(&path).unwrap();
+
+        assert_eq!(manifest.name.as_deref(), Some("foo-cli"));
+        assert_eq!(manifest.version.as_deref(), Some("1.2.0_beta.1"));
+        assert_eq!(manifest.description.as_deref(), Some("A foo tool"));
+        assert_eq!(manifest.url.as_deref(), Some("https://example.com/foo"));
+    }
+
+    #[test]
+    fn missing_package_json_reads_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(read_package_json(&dir.path().join("package.json")).is_none());
+    }
+}
@@ -1,97 +1,765 @@
 //! pkgbuild module handles the generation of pkgbuild
-use crate::utils::dead;
+use log::info;
+
+use crate::checksum::source_filename;
+use crate::config::Config;
+use crate::error::AurdersError;
+use crate::templates::Templates;
+use crate::utils::{
+    escape_double_quoted, fill_lines, format_array, make_way_for, optional_value, parse_backup,
+    parse_depends, parse_list, parse_optdepends, print_prompt, quote_array, track_created,
+    unknown_placeholders,
+};
 use crate::Information;
 
+use std::error::Error;
 use std::fs::{self, File};
 use std::io::{self, BufRead, Write};
+use std::path::Path;
 
-/// generate_pkgbuild generates and returns the PKGBUILD
-pub fn generate_pkgbuild(pkginfo: &Information) {
-    let template = get_template();
-    let pkgbuild: String;
-
-    let build_commands = get_build_commands();
-    let package_commands = get_package_commands();
-
-    match template {
-        Ok(output) => {
-            println!("\nGot PKGBUILD template.");
-            pkgbuild = output
-                .replace("{maintainer_name}", &pkginfo.maintainer_name)
-                .replace("{maintainer_email}", &pkginfo.maintainer_email)
-                .replace("{pkgname}", &pkginfo.pkgname)
-                .replace("{pkgver}", &pkginfo.pkgver)
-                .replace("{pkgrel}", &pkginfo.pkgrel)
-                .replace("{pkgdesc}", &pkginfo.pkgdesc)
-                .replace("{arch}", &pkginfo.arch)
-                .replace("{url}", &pkginfo.url)
-                .replace("{license}", &pkginfo.license)
-                .replace("{depends}", &pkginfo.depends)
-                .replace("{makedepends}", &pkginfo.makedepends)
-                .replace("{source}", &pkginfo.source)
-                .replace("{sha256sums}", &pkginfo.sha256sums)
-                .replace("{build}", &build_commands)
-                .replace("{package}", &package_commands);
-
-            save_pkgbuild(&pkgbuild);
-        }
-        Err(e) => {
-            eprintln!("Failed to generate PKGBUILD from template: {}.", e);
-            dead();
-        }
+/// PKGVER_FUNCTION is the standard pkgver() of VCS packages, deriving version from latest git tag
+const PKGVER_FUNCTION: &str = r#"pkgver() {
+    cd "${pkgname%-git}"
+    git describe --long --tags --abbrev=7 | sed 's/^v//;s/\([^-]*-g\)/r\1/;s/-/./g'
+}
+"#;
+
+/// INSTALL_SKELETON is the install scriptlet offered to packages that have none yet
+const INSTALL_SKELETON: &str = r#"post_install() {
+    :
+}
+
+post_upgrade() {
+    :
+}
+
+post_remove() {
+    :
+}
+"#;
+
+/// PLACEHOLDERS are the placeholders generate_pkgbuild knows how to fill in PKGBUILD template
+const PLACEHOLDERS: &[&str] = &[
+    "maintainer_name",
+    "maintainer_email",
+    "extra",
+    "pkgbase",
+    "pkgname",
+    "pkgver",
+    "pkgrel",
+    "epoch",
+    "pkgdesc",
+    "arch",
+    "url",
+    "license",
+    "depends",
+    "makedepends",
+    "checkdepends",
+    "optdepends",
+    "provides",
+    "conflicts",
+    "replaces",
+    "backup",
+    "options",
+    "install",
+    "changelog",
+    "groups",
+    "noextract",
+    "validpgpkeys",
+    "source",
+    "checksum_array",
+    "checksums",
+    "arch_sources",
+    "pkgver_function",
+    "prepare_function",
+    "build",
+    "package_functions",
+];
+
+/// generate_pkgbuild generates and returns the PKGBUILD from the PKGBUILD template of templates
+pub fn generate_pkgbuild(
+    pkginfo: &Information,
+    templates: &Templates,
+) -> Result<String, Box<dyn Error>> {
+    if pkginfo.pkgname.is_empty() {
+        return Err("package has no name".into());
+    }
+    pkginfo.validate()?;
+
+    // left in place, an unknown placeholder would silently end up in the PKGBUILD
+    let unknown = unknown_placeholders(
+        &templates.pkgbuild,
+        &templates.known_placeholders(PLACEHOLDERS),
+    );
+    if !unknown.is_empty() {
+        return Err(format!("unknown placeholders in template: {}", unknown.join(", ")).into());
+    }
+
+    let package_functions = package_functions(&pkginfo.pkgname)
+        .iter()
+        .zip(&pkginfo.package)
+        .map(|(function, commands)| {
+            format!("{}() {{\n    {}\n}}", function, function_body(commands))
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n");
+
+    // split packages declare pkgbase along with an array of names, single package keeps the
+    // classic pkgname=foo form
+    let (pkgbase, pkgname) = if pkginfo.pkgname.len() > 1 {
+        (
+            vec![pkginfo.pkgbase.clone()],
+            format!("({})", quote_array(&pkginfo.pkgname)),
+        )
+    } else {
+        (Vec::new(), pkginfo.pkgname[0].clone())
+    };
+
+    // each optional dependency goes on a line of its own, aligned under the first one
+    let optdepends = format_array(&parse_optdepends(&pkginfo.optdepends), '\'', 12);
+
+    // sources are double quoted so that variables like $pkgver are expanded, which leaves quotes
+    // and command substitutions in them unescapable
+    let unquotable = pkginfo
+        .source
+        .iter()
+        .chain(
+            pkginfo
+                .arch_source
+                .iter()
+                .flat_map(|arch_source| &arch_source.source),
+        )
+        .find(|source| source.contains(['"', '`']) || source.contains("$("));
+    if let Some(source) = unquotable {
+        return Err(format!(
+            "source {} contains a double quote, backtick or $(, which cannot be quoted",
+            source
+        )
+        .into());
+    }
+
+    let source = format_array(&pkginfo.source, '"', 8);
+    let checksum_array = pkginfo.checksum_algorithm.array();
+    let checksums = format_array(&pkginfo.paired_checksums(), '\'', checksum_array.len() + 2);
+
+    // source_<arch> and its checksums, each array aligned the same way as the plain ones
+    let arch_sources: Vec<String> = pkginfo
+        .arch_source
+        .iter()
+        .flat_map(|arch_source| {
+            let arch = &arch_source.arch;
+            [
+                format!(
+                    "source_{}=({})",
+                    arch,
+                    format_array(&arch_source.source, '"', arch.len() + 9)
+                ),
+                format!(
+                    "{}_{}=({})",
+                    checksum_array,
+                    arch,
+                    format_array(
+                        &arch_source.paired_checksums(),
+                        '\'',
+                        checksum_array.len() + arch.len() + 3
+                    )
+                ),
+            ]
+        })
+        .collect();
+
+    let prepare_function = if pkginfo.prepare.is_empty() {
+        Vec::new()
+    } else {
+        vec![format!("prepare() {{\n    {}\n}}\n", pkginfo.prepare)]
     };
+
+    let pkgver_function = if pkginfo.vcs {
+        vec![PKGVER_FUNCTION.to_string()]
+    } else {
+        Vec::new()
+    };
+
+    // optional lines are filled first, they are dropped from template when left blank
+    let mut output = fill_lines(&templates.pkgbuild, "{pkgbase}", &pkgbase);
+    output = fill_lines(&output, "{epoch}", &optional_value(&pkginfo.epoch));
+    output = fill_lines(&output, "{pkgver_function}", &pkgver_function);
+    output = fill_lines(
+        &output,
+        "{checkdepends}",
+        &array_line(parse_depends(&pkginfo.checkdepends)),
+    );
+    output = fill_lines(&output, "{optdepends}", &optional_value(&optdepends));
+    output = fill_lines(
+        &output,
+        "{provides}",
+        &array_line(parse_depends(&pkginfo.provides)),
+    );
+    output = fill_lines(
+        &output,
+        "{conflicts}",
+        &array_line(parse_depends(&pkginfo.conflicts)),
+    );
+    output = fill_lines(
+        &output,
+        "{replaces}",
+        &array_line(parse_depends(&pkginfo.replaces)),
+    );
+    output = fill_lines(
+        &output,
+        "{backup}",
+        &array_line(parse_backup(&pkginfo.backup)),
+    );
+    output = fill_lines(
+        &output,
+        "{options}",
+        &array_line(parse_list(&pkginfo.options)),
+    );
+    output = fill_lines(&output, "{install}", &optional_value(&pkginfo.install));
+    output = fill_lines(&output, "{changelog}", &optional_value(&pkginfo.changelog));
+    output = fill_lines(
+        &output,
+        "{groups}",
+        &array_line(parse_list(&pkginfo.groups)),
+    );
+    output = fill_lines(
+        &output,
+        "{noextract}",
+        &array_line(parse_list(&pkginfo.noextract)),
+    );
+    output = fill_lines(
+        &output,
+        "{validpgpkeys}",
+        &array_line(parse_list(&pkginfo.validpgpkeys)),
+    );
+
+    let pkgbuild = templates.fill_placeholders(
+        &output,
+        &[
+            ("maintainer_name", &pkginfo.maintainer_name),
+            ("maintainer_email", &pkginfo.maintainer_email),
+            ("pkgname", &pkgname),
+            ("pkgver", &pkginfo.pkgver),
+            ("pkgrel", &pkginfo.pkgrel),
+            ("pkgdesc", &escape_double_quoted(&pkginfo.pkgdesc)),
+            ("arch", &quote_array(&pkginfo.arch)),
+            ("url", &escape_double_quoted(&pkginfo.url)),
+            ("license", &quote_array(&parse_list(&pkginfo.license))),
+            ("depends", &quote_array(&parse_depends(&pkginfo.depends))),
+            (
+                "makedepends",
+                &quote_array(&parse_depends(&pkginfo.makedepends)),
+            ),
+            ("source", &source),
+            ("checksum_array", checksum_array),
+            ("checksums", &checksums),
+            ("build", function_body(&pkginfo.build)),
+            ("package_functions", &package_functions),
+        ],
+    );
+
+    // filled last, so that variables like ${pkgver} in sources are not taken for placeholders
+    let pkgbuild = fill_lines(&pkgbuild, "{arch_sources}", &arch_sources);
+    let pkgbuild = fill_lines(&pkgbuild, "{prepare_function}", &prepare_function);
+    let pkgbuild = fill_lines(&pkgbuild, "{extra}", &pkginfo.extra);
+
+    Ok(pkgbuild)
 }
 
-/// get_template retrieves and returns the contents of templates/PKGBUILD
-fn get_template() -> std::io::Result<String> {
-    let contents_vec = match fs::read("templates/PKGBUILD") {
-        Ok(output) => output,
-        Err(e) => {
-            eprintln!("Failed to read templates/PKGBUILD: {}.", e);
-            dead();
-            return Err(e);
-        }
+/// prepare_commands returns the body of prepare() applying every patch among source, in order, to
+/// the sources extracted to srcdir. None when there are no patches.
+pub fn prepare_commands(source: &[String], srcdir: &str) -> Option<String> {
+    let patches: Vec<String> = source
+        .iter()
+        .map(|source| source_filename(source))
+        .filter(|file| file.ends_with(".patch") || file.ends_with(".diff"))
+        .collect();
+
+    if patches.is_empty() {
+        return None;
+    }
+
+    let mut commands = vec![format!("cd \"{}\"", srcdir)];
+    commands.extend(
+        patches
+            .iter()
+            .map(|patch| format!("patch -Np1 -i \"$srcdir/{}\"", patch)),
+    );
+
+    Some(commands.join("\n    "))
+}
+
+/// service_unit returns a minimal systemd service running /usr/bin/name, described as
+/// description, for the maintainer to adjust
+pub fn service_unit(name: &str, description: &str) -> String {
+    format!(
+        "[Unit]\nDescription={}\nAfter=network.target\n\n[Service]\nType=simple\nExecStart=/usr/bin/{}\nRestart=on-failure\n\n[Install]\nWantedBy=multi-user.target\n",
+        description, name
+    )
+}
+
+/// service_command returns the command installing unit, a source of package, to the directory
+/// of systemd system units
+pub fn service_command(unit: &str) -> String {
+    format!(
+        "install -Dm644 \"$srcdir/{}\" \"$pkgdir/usr/lib/systemd/system/{}\"",
+        unit, unit
+    )
+}
+
+/// license_commands returns the commands installing files, the license texts of source extracted
+/// to srcdir, to /usr/share/licenses/$pkgname, which packages of a custom license (custom,
+/// custom:NAME or LicenseRef-NAME) have to do. None when license is not a custom one.
+pub fn license_commands(license: &str, files: &[String], srcdir: &str) -> Option<String> {
+    let custom = parse_list(license).iter().any(|license| {
+        license == "custom" || license.starts_with("custom:") || license.starts_with("LicenseRef-")
+    });
+
+    if !custom {
+        return None;
+    }
+
+    let files = if files.is_empty() {
+        vec!["LICENSE".to_string()]
+    } else {
+        files.to_vec()
     };
 
-    let contents = match String::from_utf8(contents_vec) {
-        Ok(output) => output,
-        Err(e) => {
-            eprintln!("Failed to convert Vec<u8> to String: {}.", e);
-            dead();
-            "ERRROOORRR".to_string()
+    Some(
+        files
+            .iter()
+            .map(|file| {
+                format!(
+                    "install -Dm644 \"$srcdir/{}/{}\" \"$pkgdir/usr/share/licenses/$pkgname/{}\"",
+                    srcdir, file, file
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n    "),
+    )
+}
+
+/// parse_pkgbuild reads the fields aurders manages back from an existing PKGBUILD, so that it can
+/// be regenerated with only some of them changed. Fields which are not in the PKGBUILD are left
+/// out of the returned config, optional ones among them being taken as blank.
+pub fn parse_pkgbuild(contents: &str) -> Result<Config, Box<dyn Error>> {
+    let mut config = Config::default();
+    let mut has_pkgname = false;
+
+    for line in contents.lines() {
+        if let Some(maintainer) = line.strip_prefix("# Maintainer:") {
+            // in the form of "Name <email>"
+            let (name, email) = match maintainer.split_once('<') {
+                Some((name, email)) => (name, email.trim_end().trim_end_matches('>')),
+                None => (maintainer, ""),
+            };
+            config.maintainer_name = Some(name.trim().to_string());
+            if !email.is_empty() {
+                config.maintainer_email = Some(email.trim().to_string());
+            }
+            continue;
         }
+
+        // only top level assignments are fields, indented ones are inside functions
+        let (name, value) = match line.split_once('=') {
+            Some((name, value)) if !name.is_empty() && !name.starts_with(char::is_whitespace) => {
+                (name, parse_value(value))
+            }
+            _ => continue,
+        };
+
+        match name {
+            "pkgbase" => config.pkgbase = Some(value),
+            "pkgname" => {
+                has_pkgname = true;
+                // names of split package are an array
+                config.pkgname = if value.starts_with('(') {
+                    parse_array(contents, "pkgname")
+                } else {
+                    Some(vec![value])
+                };
+            }
+            "pkgver" => config.pkgver = Some(value),
+            "pkgrel" => config.pkgrel = Some(value),
+            "epoch" => config.epoch = Some(value),
+            "pkgdesc" => config.pkgdesc = Some(value),
+            "url" => config.url = Some(value),
+            "install" => config.install = Some(value),
+            "changelog" => config.changelog = Some(value),
+            _ => (),
+        };
+    }
+
+    if !has_pkgname {
+        return Err("PKGBUILD has no pkgname".into());
+    }
+
+    for optional in [
+        &mut config.epoch,
+        &mut config.url,
+        &mut config.install,
+        &mut config.changelog,
+    ] {
+        optional.get_or_insert_with(String::new);
+    }
+
+    Ok(config)
+}
+
+/// MANAGED_FIELDS are the top level assignments of PKGBUILD aurders generates, source_<arch> and
+/// checksum arrays (of any algorithm, as aurders computes its own) being recognized by their names
+const MANAGED_FIELDS: &[&str] = &[
+    "pkgbase",
+    "pkgname",
+    "pkgver",
+    "pkgrel",
+    "epoch",
+    "pkgdesc",
+    "arch",
+    "url",
+    "license",
+    "groups",
+    "depends",
+    "makedepends",
+    "checkdepends",
+    "optdepends",
+    "provides",
+    "conflicts",
+    "replaces",
+    "backup",
+    "options",
+    "install",
+    "changelog",
+    "source",
+    "noextract",
+    "validpgpkeys",
+];
+
+/// is_managed tells whether name is the name of a field aurders generates
+fn is_managed(name: &str) -> bool {
+    MANAGED_FIELDS.contains(&name)
+        || name.starts_with("source_")
+        || name.ends_with("sums")
+        || name.contains("sums_")
+}
+
+/// pkgbuild_defaults parses a PKGBUILD like parse_pkgbuild, taking its arrays as well, with
+/// values joined the way they are entered at prompts. Meant as the defaults of prompts.
+pub fn pkgbuild_defaults(contents: &str) -> Result<Config, Box<dyn Error>> {
+    let mut config = parse_pkgbuild(contents)?;
+    let joined = |name: &str, separator: &str| {
+        parse_array(contents, name).map(|values| values.join(separator))
     };
 
-    Ok(contents)
+    config.license = joined("license", " ");
+    config.arch = parse_array(contents, "arch");
+    config.depends = joined("depends", " ");
+    config.makedepends = joined("makedepends", " ");
+    config.checkdepends = joined("checkdepends", " ");
+    config.optdepends = joined("optdepends", "; ");
+    config.provides = joined("provides", " ");
+    config.conflicts = joined("conflicts", " ");
+    config.replaces = joined("replaces", " ");
+    config.groups = joined("groups", " ");
+    config.backup = joined("backup", " ");
+    config.options = joined("options", " ");
+    config.noextract = joined("noextract", " ");
+    config.validpgpkeys = joined("validpgpkeys", " ");
+
+    Ok(config)
 }
 
-/// save_pkgbuild is a helper function to save PKGBUILD to disk
-fn save_pkgbuild(pkgbuild: &String) {
-    // create_new because it creates new file in read-write mode; errror if the file exists
-    // and making sure that possibly existing PKGBUILD does not get overwritten
-    let file_result = File::create_new("aurders/PKGBUILD");
+/// unmanaged_fields returns the top level assignments of PKGBUILD aurders does not generate
+/// (e.g. _commit=...), arrays spanning many lines included, as they are written
+pub fn unmanaged_fields(contents: &str) -> Vec<String> {
+    let lines: Vec<&str> = contents.lines().collect();
 
-    match file_result {
-        Ok(mut file) => match file.write_all(pkgbuild.as_bytes()) {
-            Ok(_) => println!("Saved PKGBUILD to disk successfully."),
-            Err(e) => {
-                eprintln!("Failed to write to PKGBUILD: {}.", e);
-                dead();
+    assignments(&lines)
+        .into_iter()
+        .filter(|(name, _, _)| !is_managed(name))
+        .map(|(_, start, end)| lines[start..=end].join("\n"))
+        .collect()
+}
+
+/// assignments returns the name, first and last line of every top level assignment among lines
+/// of PKGBUILD, an array going on until its closing parenthesis
+fn assignments(lines: &[&str]) -> Vec<(String, usize, usize)> {
+    let mut found = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let start = index;
+        index += 1;
+
+        let (name, value) = match lines[start].split_once('=') {
+            Some((name, value))
+                if !name.is_empty()
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+            {
+                (name, value)
+            }
+            _ => continue,
+        };
+
+        if let Some(array) = value.strip_prefix('(') {
+            let mut quote: Option<char> = None;
+            let mut escaped = false;
+            let mut text = array.to_string();
+
+            // parentheses inside quotes (e.g. of optdepends) do not close the array
+            'array: loop {
+                for c in text.chars() {
+                    match (quote, c) {
+                        _ if escaped => escaped = false,
+                        (Some('\''), '\'') => quote = None,
+                        (Some('\''), _) => (),
+                        (_, '\\') => escaped = true,
+                        (Some(q), c) if c == q => quote = None,
+                        (Some(_), _) => (),
+                        (None, '\'' | '"') => quote = Some(c),
+                        (None, ')') => break 'array,
+                        _ => (),
+                    }
+                }
+
+                match lines.get(index) {
+                    Some(line) => {
+                        text = line.to_string();
+                        index += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        found.push((name.to_string(), start, index - 1));
+    }
+
+    found
+}
+
+/// merge_pkgbuild rewrites the fields aurders generates in existing PKGBUILD with their values in
+/// generated one, leaving everything else (functions, comments, fields of its own) as it is.
+/// Fields existing one lacks are added after its last generated field, those generated one no
+/// longer has (e.g. md5sums) are removed.
+pub fn merge_pkgbuild(existing: &str, generated: &str) -> String {
+    let generated_lines: Vec<&str> = generated.lines().collect();
+    let mut fields: Vec<(String, String)> = assignments(&generated_lines)
+        .into_iter()
+        .filter(|(name, _, _)| is_managed(name))
+        .map(|(name, start, end)| (name, generated_lines[start..=end].join("\n")))
+        .collect();
+
+    let lines: Vec<&str> = existing.lines().collect();
+    let mut merged: Vec<String> = Vec::new();
+    let mut last_field = None;
+    let mut index = 0;
+
+    for (name, start, end) in assignments(&lines) {
+        merged.extend(lines[index..start].iter().map(|line| line.to_string()));
+        index = end + 1;
+
+        if !is_managed(&name) {
+            merged.extend(lines[start..=end].iter().map(|line| line.to_string()));
+            continue;
+        }
+
+        if let Some(position) = fields.iter().position(|(field, _)| *field == name) {
+            merged.push(fields.remove(position).1);
+            last_field = Some(merged.len());
+        }
+    }
+    merged.extend(lines[index..].iter().map(|line| line.to_string()));
+
+    let at = last_field.unwrap_or(merged.len());
+    merged.splice(at..at, fields.into_iter().map(|(_, field)| field));
+
+    let mut merged = merged.join("\n");
+    merged.push('\n');
+    merged
+}
+
+/// read_pkgbuild reads the PKGBUILD at path and parses it with parse_pkgbuild
+pub fn read_pkgbuild(path: &Path) -> Result<Config, Box<dyn Error>> {
+    parse_pkgbuild(&fs::read_to_string(path)?)
+}
+
+/// parse_array returns the values of top level name=(...) array of PKGBUILD without their quotes,
+/// or None when PKGBUILD has no such array
+pub fn parse_array(contents: &str, name: &str) -> Option<Vec<String>> {
+    let prefix = format!("{}=(", name);
+    let start = contents
+        .match_indices(&prefix)
+        .find(|(index, _)| *index == 0 || contents[..*index].ends_with('\n'))?
+        .0
+        + prefix.len();
+
+    let mut values: Vec<String> = Vec::new();
+    let mut value = String::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for c in contents[start..].chars() {
+        // backslash takes the next character as it is, outside of single quotes ('\'' included)
+        if escaped {
+            value.push(c);
+            escaped = false;
+            continue;
+        }
+
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), c) => value.push(c),
+            (Some(q), c) if c == q => quote = None,
+            (_, '\\') => escaped = true,
+            (Some(_), c) => value.push(c),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, ')') => break,
+            (None, c) if c.is_whitespace() => {
+                if !value.is_empty() {
+                    values.push(std::mem::take(&mut value));
+                }
+            }
+            (None, c) => value.push(c),
+        };
+    }
+
+    if !value.is_empty() {
+        values.push(value);
+    }
+
+    Some(values)
+}
+
+/// parse_value returns the value of a PKGBUILD assignment without its quotes (and the escapes of
+/// double quoted ones), or up to the first whitespace (e.g. of a trailing comment) when it is not
+/// quoted
+fn parse_value(value: &str) -> String {
+    let value = value.trim();
+
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut unquoted = String::new();
+        let mut chars = quoted.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                // inside double quotes, backslash escapes only the characters special there
+                '\\' => match chars.next() {
+                    Some(next @ ('\\' | '"' | '$' | '`')) => unquoted.push(next),
+                    Some(next) => {
+                        unquoted.push(c);
+                        unquoted.push(next);
+                    }
+                    None => unquoted.push(c),
+                },
+                _ => unquoted.push(c),
             }
-        },
-        Err(e) => {
-            eprintln!("Failed to create new PKGBUILD: {}.", e);
-            dead();
         }
+
+        return unquoted;
+    }
+
+    if let Some(quoted) = value.strip_prefix('\'') {
+        return match quoted.find('\'') {
+            Some(end) => quoted[..end].to_string(),
+            None => quoted.to_string(),
+        };
+    }
+
+    match value.split_once(char::is_whitespace) {
+        Some((value, _)) => value.to_string(),
+        None => value.to_string(),
+    }
+}
+
+/// function_body returns commands as the body of a function, which bash does not allow to be
+/// empty, : doing nothing in their place when there are none
+fn function_body(commands: &str) -> &str {
+    if commands.trim().is_empty() {
+        ":"
+    } else {
+        commands
     }
 }
 
+/// array_line returns the quoted values as the only entry for fill_lines, or nothing when there
+/// are no values so that the optional array is left out
+fn array_line(values: Vec<String>) -> Vec<String> {
+    optional_value(&quote_array(&values))
+}
+
+/// save_pkgbuild is a helper function to save PKGBUILD to output_dir, asking what to do with an
+/// existing one unless forced
+pub fn save_pkgbuild(
+    pkgbuild: &String,
+    output_dir: &Path,
+    force: bool,
+) -> Result<(), AurdersError> {
+    let path = output_dir.join("PKGBUILD");
+
+    make_way_for(&path, force)?;
+
+    let mut file = File::create(&path)
+        .map_err(|e| AurdersError::Failed(format!("Failed to create new PKGBUILD: {}", e)))?;
+    file.write_all(pkgbuild.as_bytes())
+        .map_err(|e| AurdersError::Failed(format!("Failed to write to PKGBUILD: {}", e)))?;
+    info!("Saved PKGBUILD to disk successfully.");
+
+    Ok(())
+}
+
+/// save_install_skeleton saves a skeleton install scriptlet named install to output_dir
+pub fn save_install_skeleton(install: &str, output_dir: &Path) -> Result<(), AurdersError> {
+    let path = output_dir.join(install);
+
+    fs::write(&path, INSTALL_SKELETON)
+        .map_err(|e| AurdersError::Failed(format!("Failed to create {}: {}", install, e)))?;
+    info!("Saved skeleton of {} to disk successfully.", install);
+
+    Ok(())
+}
+
+/// save_changelog saves an empty changelog named changelog to output_dir, to be filled by the
+/// maintainer
+pub fn save_changelog(changelog: &str, output_dir: &Path) -> Result<(), AurdersError> {
+    let path = output_dir.join(changelog);
+
+    fs::write(&path, "")
+        .map_err(|e| AurdersError::Failed(format!("Failed to create {}: {}", changelog, e)))?;
+    info!("Saved empty {} to disk successfully.", changelog);
+
+    Ok(())
+}
+
+/// save_service_unit saves the scaffold of systemd service unit to output_dir, keeping an
+/// existing one
+pub fn save_service_unit(
+    unit: &str,
+    contents: &str,
+    output_dir: &Path,
+) -> Result<(), AurdersError> {
+    let path = output_dir.join(unit);
+
+    if path.exists() {
+        info!("Using existing {}.", unit);
+        return Ok(());
+    }
+
+    fs::write(&path, contents)
+        .map_err(|e| AurdersError::Failed(format!("Failed to create {}: {}", unit, e)))?;
+    track_created(&path);
+    info!("Saved scaffold of {} to disk successfully.", unit);
+
+    Ok(())
+}
+
 /// get_build_commads gets the build commands from user and returns it
-fn get_build_commands() -> String {
+pub fn get_build_commands() -> String {
     let mut build = String::new();
     let stdin = io::stdin();
 
-    println!("\nEnter commands to add in build(). [\"qq\" or EOF signal to quit]");
+    print_prompt("\nEnter commands to add in build(). [\"qq\" or EOF signal to quit]\n");
 
     // lock the stdin and take multiline input correctly
     for line in stdin.lock().lines() {
@@ -113,12 +781,28 @@ fn get_build_commands() -> String {
     build.trim().to_string()
 }
 
-/// get_package_commads gets the package commands from user and returns it
-fn get_package_commands() -> String {
+/// package_functions returns the name of package() function, or a package_name() function for
+/// each of the split packages
+pub fn package_functions(pkgname: &[String]) -> Vec<String> {
+    if pkgname.len() > 1 {
+        pkgname
+            .iter()
+            .map(|name| format!("package_{}", name))
+            .collect()
+    } else {
+        vec!["package".to_string()]
+    }
+}
+
+/// get_package_commads gets the package commands of given function from user and returns it
+pub fn get_package_commands(function: &str) -> String {
     let mut package = String::new();
     let stdin = io::stdin();
 
-    println!("\nEnter commands to add in package(). [\"qq\" or EOF signal to quit]");
+    print_prompt(&format!(
+        "\nEnter commands to add in {}(). [\"qq\" or EOF signal to quit]\n",
+        function
+    ));
 
     for line in stdin.lock().lines() {
         match line {
@@ -138,3 +822,177 @@ fn get_package_commands() -> String {
 
     package.trim().to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::tests::information;
+
+    #[test]
+    fn merge_keeps_custom_build() {
+        let existing = "\
+# Maintainer: Jane Doe <jane@example.com>
+pkgname=foo
+pkgver=1.0.0
+pkgrel=2
+source=(\"foo-$pkgver.tar.gz\")
+sha256sums=('aaa')
+_flags=--with-custom
+
+build() {
+  cd \"$srcdir/foo-$pkgver\"
+  ./configure $_flags
+  make
+}
+";
+        let generated = "\
+# Maintainer: Jane Doe <jane@example.com>
+pkgname=foo
+pkgver=1.1.0
+pkgrel=1
+source=(\"foo-$pkgver.tar.gz\")
+sha256sums=('bbb')
+
+build() {
+  :
+}
+";
+
+        let merged = merge_pkgbuild(existing, generated);
+
+        assert!(merged.contains("pkgver=1.1.0\npkgrel=1\n"));
+        assert!(merged.contains("sha256sums=('bbb')\n_flags=--with-custom\n"));
+        assert!(merged.contains("  ./configure $_flags\n  make\n}"));
+        assert!(!merged.contains("pkgver=1.0.0"));
+        assert_eq!(merged.matches("build() {").count(), 1);
+    }
+
+    #[test]
+    fn fills_every_placeholder() {
+        let templates = Templates {
+            pkgbuild: "{maintainer_name}|{maintainer_email}|{pkgname}|{pkgver}|{pkgrel}|{pkgdesc}|{url}|{arch}|{license}|{depends}|{makedepends}|{source}|{checksum_array}|{checksums}|{build}\n".to_string(),
+            srcinfo: String::new(),
+            vars: Vec::new(),
+        };
+
+        let pkgbuild = generate_pkgbuild(&information(), &templates).unwrap();
+
+        assert_eq!(
+            pkgbuild,
+            "Jane Doe|jane@example.com|foo|1.0.0|1|A foo tool|https://example.com/foo|'x86_64'|'MIT'|'glibc'|'cmake'|\"foo-$pkgver.tar.gz\"|sha256sums|'abc123'|make\n"
+        );
+    }
+
+    #[test]
+    fn leaves_no_placeholders() {
+        let pkgbuild = generate_pkgbuild(&information(), &Templates::default()).unwrap();
+
+        assert!(unknown_placeholders(&pkgbuild, &[]).is_empty());
+        assert!(pkgbuild.contains("\npkgver=1.0.0\n"));
+        assert!(pkgbuild.contains("\nbuild() {\n    make\n}\n"));
+    }
+
+    #[test]
+    fn names_unknown_placeholder() {
+        let templates = Templates {
+            pkgbuild: "pkgname={pkgname}\nfoo=({foo})\n".to_string(),
+            srcinfo: String::new(),
+            vars: Vec::new(),
+        };
+
+        let error = generate_pkgbuild(&information(), &templates)
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("foo"));
+    }
+
+    #[test]
+    fn refuses_incomplete_information() {
+        let mut pkginfo = information();
+        pkginfo.pkgver = String::new();
+
+        assert!(generate_pkgbuild(&pkginfo, &Templates::default()).is_err());
+    }
+
+    #[test]
+    fn saves_pkgbuild_to_output_dir() {
+        let dir = tempfile::tempdir().unwrap();
+
+        save_pkgbuild(&"pkgname=foo\n".to_string(), dir.path(), false).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("PKGBUILD")).unwrap(),
+            "pkgname=foo\n"
+        );
+    }
+
+    #[test]
+    fn forced_save_overwrites_pkgbuild() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("PKGBUILD"), "pkgname=old\n").unwrap();
+
+        save_pkgbuild(&"pkgname=new\n".to_string(), dir.path(), true).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("PKGBUILD")).unwrap(),
+            "pkgname=new\n"
+        );
+    }
+
+    #[test]
+    fn escapes_quote_and_dollar_in_pkgdesc() {
+        let mut pkginfo = information();
+        pkginfo.pkgdesc = "A \"foo\" tool for $HOME".to_string();
+
+        let pkgbuild = generate_pkgbuild(&pkginfo, &Templates::default()).unwrap();
+
+        assert!(pkgbuild.contains("\npkgdesc=\"A \\\"foo\\\" tool for \\$HOME\"\n"));
+    }
+
+    #[test]
+    fn custom_license_is_installed() {
+        let commands = license_commands("custom", &[], "$pkgname-$pkgver").unwrap();
+
+        assert_eq!(
+            commands,
+            "install -Dm644 \"$srcdir/$pkgname-$pkgver/LICENSE\" \"$pkgdir/usr/share/licenses/$pkgname/LICENSE\""
+        );
+        assert!(license_commands(
+            "MIT LicenseRef-Foo",
+            &["COPYING".to_string()],
+            "$pkgname-$pkgver"
+        )
+        .unwrap()
+        .contains("\"$pkgdir/usr/share/licenses/$pkgname/COPYING\""));
+        assert_eq!(license_commands("MIT", &[], "$pkgname-$pkgver"), None);
+    }
+
+    #[test]
+    fn fills_custom_template_var() {
+        let templates = Templates {
+            pkgbuild: "pkgname={pkgname}\n_commit={commit}\n".to_string(),
+            srcinfo: String::new(),
+            vars: vec![
+                ("commit".to_string(), "0123abc".to_string()),
+                ("pkgname".to_string(), "bar".to_string()),
+            ],
+        };
+
+        let pkgbuild = generate_pkgbuild(&information(), &templates).unwrap();
+
+        assert_eq!(pkgbuild, "pkgname=foo\n_commit=0123abc\n");
+    }
+
+    #[test]
+    fn no_depends_is_an_empty_array() {
+        let mut pkginfo = information();
+        pkginfo.depends = String::new();
+
+        let pkgbuild = generate_pkgbuild(&pkginfo, &Templates::default()).unwrap();
+
+        assert!(parse_depends("").is_empty());
+        assert!(parse_depends("  ,  ").is_empty());
+        assert!(pkgbuild.contains("\ndepends=()\n"));
+    }
+}
@@ -0,0 +1,61 @@
+//! preset module holds the defaults of common package types, which the prompts start from
+use crate::build_system::BuildSystem;
+
+/// Preset is a common type of package, pre-filling its dependencies and build system
+#[derive(Clone, Copy, PartialEq)]
+pub enum Preset {
+    CliTool,
+    PythonApp,
+    RustBin,
+    Git,
+}
+
+impl Preset {
+    /// from_name returns the preset of given name
+    pub fn from_name(name: &str) -> Option<Preset> {
+        match name {
+            "cli-tool" => Some(Preset::CliTool),
+            "python-app" => Some(Preset::PythonApp),
+            "rust-bin" => Some(Preset::RustBin),
+            "git" => Some(Preset::Git),
+            _ => None,
+        }
+    }
+
+    /// depends returns the default dependencies of packages of this type
+    pub fn depends(&self) -> &'static str {
+        match self {
+            Preset::PythonApp => "python",
+            Preset::RustBin => "gcc-libs glibc",
+            Preset::CliTool | Preset::Git => "",
+        }
+    }
+
+    /// makedepends returns the default make dependencies of packages of this type
+    pub fn makedepends(&self) -> &'static str {
+        match self {
+            Preset::CliTool => "",
+            Preset::PythonApp => "python-build python-installer python-wheel",
+            Preset::RustBin => "cargo",
+            Preset::Git => "git",
+        }
+    }
+
+    /// build_system returns the build system packages of this type are built with
+    pub fn build_system(&self) -> BuildSystem {
+        match self {
+            Preset::CliTool => BuildSystem::Make,
+            Preset::PythonApp => BuildSystem::Python,
+            Preset::RustBin => BuildSystem::Cargo,
+            Preset::Git => BuildSystem::None,
+        }
+    }
+
+    /// vcs returns whether packages of this type are VCS packages, None if that is up to the user
+    pub fn vcs(&self) -> Option<bool> {
+        match self {
+            Preset::Git => Some(true),
+            _ => None,
+        }
+    }
+}
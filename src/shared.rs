@@ -1,80 +1,1353 @@
 //! shared module contains the data that is shared among others
-use crate::args::handle_args;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, IsTerminal};
+
+use log::info;
+use serde::Serialize;
+
+use crate::args::Args;
+use crate::build_system::{select_build_system, BuildSystem};
+use crate::checksum::{
+    arch_source_checksums, expand_source_dirs, is_remote, is_vcs, pair_checksums, select_checksum,
+    source_checksums, source_filename, ChecksumAlgorithm,
+};
+use crate::config::{
+    read_config, read_user_config, write_config, write_user_config, Config, UserConfig,
+};
+use crate::error::AurdersError;
+use crate::manifest::read_manifest;
+use crate::pkgbuild::{
+    get_build_commands, get_package_commands, license_commands, package_functions,
+    pkgbuild_defaults, prepare_commands, read_pkgbuild, save_service_unit, service_command,
+    service_unit, unmanaged_fields,
+};
+use crate::tui;
 use crate::utils::{
-    create_directory, create_tarball, get_sha256, get_source, get_templates, input_string, input_string_strict, select_arch
+    batch, check_writable, create_directory, create_tarball, default_arch, detect_license,
+    fail_in_batch, get_arch_sources, get_source, get_templates, get_vcs_source, input_bool,
+    input_pkgname, input_string, input_string_strict, input_string_validated, is_archive,
+    is_signature, license_files, parse_backup, parse_depends, parse_list, parse_optdepends,
+    pkgdesc_warnings, print_error, resolve_licenses, select_arch, sign_tarball, suggest_source,
+    tui, unknown_archs, unknown_licenses, unknown_options, validate_arch, validate_email,
+    validate_epoch, validate_fingerprint, validate_pkgname, validate_pkgrel, validate_pkgver,
+    validate_url,
 };
 
 /// Information stores the required information about package
+#[derive(Default, Serialize)]
 pub struct Information {
     pub maintainer_name: String,
     pub maintainer_email: String,
-    pub pkgname: String,
+    pub pkgbase: String,
+    /// more than one name makes it a split package
+    pub pkgname: Vec<String>,
     pub pkgver: String,
     pub pkgrel: String,
+    /// blank when package has no epoch
+    pub epoch: String,
     pub pkgdesc: String,
     pub url: String,
     pub license: String,
-    pub arch: String,
+    pub arch: Vec<String>,
     pub depends: String,
     pub makedepends: String,
-    pub source: String,
-    pub sha256sums: String,
+    pub checkdepends: String,
+    /// semicolon separated entries in the form of "foo: does X"
+    pub optdepends: String,
+    pub provides: String,
+    pub conflicts: String,
+    pub replaces: String,
+    pub groups: String,
+    pub install: String,
+    /// blank when package has no changelog
+    pub changelog: String,
+    pub backup: String,
+    pub options: String,
+    pub source: Vec<String>,
+    /// file names of the sources makepkg leaves unextracted
+    pub noextract: String,
+    /// fingerprints of the PGP keys signatures among sources are verified with
+    pub validpgpkeys: String,
+    pub checksum_algorithm: ChecksumAlgorithm,
+    /// checksum of each source, in the same order as source
+    pub checksums: Vec<String>,
+    /// sources specific to architectures, in the same order as arch, only of packages built for
+    /// more than one architecture
+    pub arch_source: Vec<ArchSource>,
+    /// VCS (-git) package derives its version from git with pkgver()
+    pub vcs: bool,
+    pub build_system: BuildSystem,
+    /// commands of prepare(), blank when package has none
+    pub prepare: String,
+    /// commands of build()
+    pub build: String,
+    /// commands of each package function, in the same order as pkgname
+    pub package: Vec<String>,
+    /// assignments aurders does not generate, kept as they are from an existing PKGBUILD
+    pub extra: Vec<String>,
+}
+
+/// ArchSource holds the sources specific to one architecture, the source_<arch> array of PKGBUILD
+#[derive(Clone, Default, Serialize)]
+pub struct ArchSource {
+    pub arch: String,
+    pub source: Vec<String>,
+    /// checksum of each source, in the same order as source
+    pub checksums: Vec<String>,
+}
+
+impl ArchSource {
+    /// paired_checksums returns one checksum for every source, in the same order, see
+    /// pair_checksums
+    pub fn paired_checksums(&self) -> Vec<String> {
+        pair_checksums(&self.source, &self.checksums)
+    }
+}
+
+impl Information {
+    /// expand_variables expands the PKGBUILD variables in value the way makepkg would, as .SRCINFO
+    /// cannot contain them
+    pub fn expand_variables(&self, value: &str) -> String {
+        // VCS sources are named after the package without its -git suffix, prebuilt binaries
+        // without its -bin suffix
+        let mut expanded = value
+            .replace("${pkgname%-git}", self.pkgname[0].trim_end_matches("-git"))
+            .replace("${pkgname%-bin}", self.pkgname[0].trim_end_matches("-bin"));
+
+        // pkgname in a split package is an array, which bash expands to its first element
+        let variables = [
+            ("pkgbase", &self.pkgbase),
+            ("pkgname", &self.pkgname[0]),
+            ("pkgver", &self.pkgver),
+            ("pkgrel", &self.pkgrel),
+            ("epoch", &self.epoch),
+            ("url", &self.url),
+        ];
+
+        for (name, replacement) in variables {
+            expanded = expanded
+                .replace(&format!("${{{}}}", name), replacement)
+                .replace(&format!("${}", name), replacement);
+        }
+
+        expanded
+    }
+
+    /// tarball_source returns the index of the source that names file once its variables are
+    /// expanded
+    pub fn tarball_source(&self, file: &str) -> Option<usize> {
+        self.source
+            .iter()
+            .position(|source| source_filename(&self.expand_variables(source)) == file)
+    }
+
+    /// add_signature adds the signature (file.sig) of the source naming file right after it, the
+    /// way makepkg verifies it, and fingerprint of the key it is signed with to validpgpkeys
+    pub fn add_signature(&mut self, file: &str, fingerprint: &str) {
+        if let Some(index) = self.tarball_source(file) {
+            let signature = format!("{}.sig", self.source[index]);
+            if !self.source.contains(&signature) {
+                self.source.insert(index + 1, signature);
+            }
+        }
+
+        if !parse_list(&self.validpgpkeys)
+            .iter()
+            .any(|key| key == fingerprint)
+        {
+            self.validpgpkeys = format!("{} {}", self.validpgpkeys, fingerprint)
+                .trim()
+                .to_string();
+        }
+    }
+
+    /// paired_checksums returns one checksum for every source, in the same order, see
+    /// pair_checksums
+    pub fn paired_checksums(&self) -> Vec<String> {
+        pair_checksums(&self.source, &self.checksums)
+    }
+
+    /// local_files returns the names of the files PKGBUILD refers to that are not downloaded by
+    /// makepkg, and so have to be in the AUR repository next to it: install scriptlet, changelog
+    /// and every local source (architecture specific ones included), each once
+    pub fn local_files(&self) -> Vec<String> {
+        let sources = self
+            .source
+            .iter()
+            .chain(self.arch_source.iter().flat_map(|arch| &arch.source))
+            .filter(|source| !is_remote(source) && !is_vcs(source))
+            .map(|source| source_filename(&self.expand_variables(source)));
+
+        let mut files: Vec<String> = Vec::new();
+        for file in [self.install.clone(), self.changelog.clone()]
+            .into_iter()
+            .chain(sources)
+        {
+            if !file.is_empty() && !files.contains(&file) {
+                files.push(file);
+            }
+        }
+
+        files
+    }
+
+    /// to_json returns every field of the package information as pretty printed JSON, for other
+    /// tools to consume
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// validate makes sure none of the fields a package cannot do without is empty, naming all of
+    /// the missing ones at once
+    pub fn validate(&self) -> Result<(), String> {
+        let required = [
+            ("maintainer_name", self.maintainer_name.is_empty()),
+            ("maintainer_email", self.maintainer_email.is_empty()),
+            ("pkgbase", self.pkgbase.is_empty()),
+            (
+                "pkgname",
+                self.pkgname.is_empty() || self.pkgname.iter().any(|name| name.is_empty()),
+            ),
+            ("pkgver", self.pkgver.is_empty()),
+            ("pkgrel", self.pkgrel.is_empty()),
+            ("pkgdesc", self.pkgdesc.trim().is_empty()),
+            ("arch", self.arch.is_empty()),
+        ];
+
+        let missing: Vec<&str> = required
+            .iter()
+            .filter(|(_, empty)| *empty)
+            .map(|(name, _)| *name)
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("required fields are empty: {}", missing.join(", ")))
+        }
+    }
+
+    /// normalize rewrites the list fields as entered ("gtk3 ,  glib2 ,cairo") to their entries
+    /// separated by single spaces ("gtk3 glib2 cairo"), optional dependencies being separated by
+    /// "; " instead
+    pub fn normalize(&mut self) {
+        let list = |value: &str| parse_list(value).join(" ");
+        let depends = |value: &str| parse_depends(value).join(" ");
+
+        self.license = list(&self.license);
+        self.groups = list(&self.groups);
+        self.options = list(&self.options);
+        self.noextract = list(&self.noextract);
+        self.validpgpkeys = list(&self.validpgpkeys);
+        self.depends = depends(&self.depends);
+        self.makedepends = depends(&self.makedepends);
+        self.checkdepends = depends(&self.checkdepends);
+        self.provides = depends(&self.provides);
+        self.conflicts = depends(&self.conflicts);
+        self.replaces = depends(&self.replaces);
+        self.backup = parse_backup(&self.backup).join(" ");
+        self.optdepends = parse_optdepends(&self.optdepends).join("; ");
+    }
+
+    /// fields returns the name and value of each field that can be reviewed and edited, lists
+    /// being joined with spaces
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("maintainer_name", self.maintainer_name.clone()),
+            ("maintainer_email", self.maintainer_email.clone()),
+            ("pkgbase", self.pkgbase.clone()),
+            ("pkgname", self.pkgname.join(" ")),
+            ("pkgver", self.pkgver.clone()),
+            ("pkgrel", self.pkgrel.clone()),
+            ("epoch", self.epoch.clone()),
+            ("pkgdesc", self.pkgdesc.clone()),
+            ("url", self.url.clone()),
+            ("license", self.license.clone()),
+            ("arch", self.arch.join(" ")),
+            ("groups", self.groups.clone()),
+            ("depends", self.depends.clone()),
+            ("makedepends", self.makedepends.clone()),
+            ("checkdepends", self.checkdepends.clone()),
+            ("optdepends", self.optdepends.clone()),
+            ("provides", self.provides.clone()),
+            ("conflicts", self.conflicts.clone()),
+            ("replaces", self.replaces.clone()),
+            ("backup", self.backup.clone()),
+            ("options", self.options.clone()),
+            ("install", self.install.clone()),
+            ("changelog", self.changelog.clone()),
+            ("source", self.source.join(" ")),
+            ("noextract", self.noextract.clone()),
+            ("validpgpkeys", self.validpgpkeys.clone()),
+        ]
+    }
+
+    /// edit_field prompts for a new value of the field named name, keeping the current one as
+    /// default. Returns false when there is no such field.
+    pub fn edit_field(&mut self, name: &str) -> Result<bool, AurdersError> {
+        let current = match self.fields().into_iter().find(|(field, _)| *field == name) {
+            Some((_, value)) => value,
+            None => return Ok(false),
+        };
+        let prompt = format!("Enter the new value of {}", name);
+
+        match name {
+            "pkgver" => self.pkgver = input_string_validated(&prompt, &current, validate_pkgver)?,
+            "pkgrel" => self.pkgrel = input_string_validated(&prompt, &current, validate_pkgrel)?,
+            "epoch" => self.epoch = input_string_validated(&prompt, &current, validate_epoch)?,
+            "url" => self.url = input_string_validated(&prompt, &current, validate_url)?,
+            "maintainer_email" => {
+                self.maintainer_email = input_string_validated(&prompt, &current, |email| {
+                    if validate_email(email) {
+                        Ok(())
+                    } else {
+                        Err(format!(
+                            "Invalid email '{}': expected the form of local@domain.tld.",
+                            email
+                        ))
+                    }
+                })?
+            }
+            "pkgname" => {
+                let pkgname = input_pkgname(&prompt, &current)?;
+
+                // single package is its own base, and every package needs a package function
+                if pkgname.len() == 1 {
+                    self.pkgbase = pkgname[0].clone();
+                }
+                let functions = package_functions(&pkgname);
+                if functions.len() != self.package.len() {
+                    self.package = functions
+                        .iter()
+                        .map(|function| get_package_commands(function))
+                        .collect();
+                }
+                self.pkgname = pkgname;
+            }
+            "arch" => {
+                self.arch = parse_list(&input_string_validated(&prompt, &current, validate_arch)?)
+            }
+            "source" => self.source = parse_list(&input_string(&prompt, &current)?),
+            _ => {
+                let value = input_string(&prompt, &current)?;
+
+                match name {
+                    "maintainer_name" => self.maintainer_name = value,
+                    "pkgbase" => self.pkgbase = value,
+                    "pkgdesc" => self.pkgdesc = value,
+                    "license" => self.license = resolve_licenses(&value),
+                    "groups" => self.groups = value,
+                    "depends" => self.depends = value,
+                    "makedepends" => self.makedepends = value,
+                    "checkdepends" => self.checkdepends = value,
+                    "optdepends" => self.optdepends = value,
+                    "provides" => self.provides = value,
+                    "conflicts" => self.conflicts = value,
+                    "replaces" => self.replaces = value,
+                    "backup" => self.backup = value,
+                    "options" => self.options = value,
+                    "install" => self.install = value,
+                    "noextract" => self.noextract = value,
+                    "validpgpkeys" => self.validpgpkeys = value,
+                    "changelog" => self.changelog = value,
+                    _ => (),
+                };
+                self.normalize();
+            }
+        };
+
+        Ok(true)
+    }
+}
+
+/// review_information shows the collected information and lets user edit fields of it until they
+/// confirm it
+fn review_information(pkginfo: &mut Information) -> Result<(), AurdersError> {
+    loop {
+        println!("\nPackage information:");
+        for (name, value) in pkginfo.fields() {
+            println!("  {:<18}{}", name, value);
+        }
+
+        if input_bool("Generate PKGBUILD and .SRCINFO with this information?(y/N): ")? {
+            return Ok(());
+        }
+
+        let name = input_string("Enter the name of field to edit", "")?;
+        if !name.is_empty() && !pkginfo.edit_field(&name)? {
+            print_error(&format!("There is no field named '{}'.", name));
+        }
+    }
+}
+
+/// existing_defaults reads the PKGBUILD already in output directory, when user wants to take its
+/// values as the defaults of prompts. Returns them along with the assignments aurders does not
+/// generate, to be kept. Batch mode and --from-pkgbuild leave it alone.
+fn existing_defaults(args: &Args) -> Result<(Config, Vec<String>), AurdersError> {
+    let path = args.output_dir.join("PKGBUILD");
+
+    if batch() || args.from_pkgbuild.is_some() || !path.is_file() {
+        return Ok((Config::default(), Vec::new()));
+    }
+
+    if !input_bool(&format!(
+        "Found {}. Use its values as defaults?(y/N): ",
+        path.display()
+    ))? {
+        return Ok((Config::default(), Vec::new()));
+    }
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Warning: failed to read {}: {}.", path.display(), e);
+            return Ok((Config::default(), Vec::new()));
+        }
+    };
+
+    match pkgbuild_defaults(&contents) {
+        Ok(defaults) => Ok((defaults, unmanaged_fields(&contents))),
+        Err(e) => {
+            eprintln!("Warning: failed to parse {}: {}.", path.display(), e);
+            Ok((Config::default(), Vec::new()))
+        }
+    }
+}
+
+/// BIN_PACKAGE is the body of package() of a package made from a prebuilt release asset, which
+/// installs the binary named after the package without its -bin suffix
+const BIN_PACKAGE: &str =
+    "install -Dm755 \"$srcdir/${pkgname%-bin}\" \"$pkgdir/usr/bin/${pkgname%-bin}\"";
+
+/// bin_source returns the source of a prebuilt release asset at url. A binary is saved under the
+/// name of package without its -bin suffix, archives keep their names for makepkg to extract them.
+fn bin_source(url: &str) -> String {
+    if is_archive(&source_filename(url)) {
+        url.to_string()
+    } else {
+        format!("${{pkgname%-bin}}::{}", url)
+    }
+}
+
+/// bin_config returns the answers making a package of the prebuilt release assets, each given for
+/// an architecture or for all of them: sources, architectures, nothing to build and a package()
+/// installing the binary. None when there are no assets.
+fn bin_config(assets: &[(Option<String>, String)]) -> Option<Config> {
+    if assets.is_empty() {
+        return None;
+    }
+
+    let mut source = Vec::new();
+    let mut arch_source: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (arch, url) in assets {
+        match arch {
+            Some(arch) => arch_source
+                .entry(arch.clone())
+                .or_default()
+                .push(bin_source(url)),
+            None => source.push(bin_source(url)),
+        }
+    }
+
+    let arch = if arch_source.is_empty() {
+        None
+    } else {
+        Some(arch_source.keys().cloned().collect())
+    };
+
+    // source_<arch> arrays are only used for packages of more than one architecture
+    if arch_source.len() == 1 {
+        if let Some((_, only)) = arch_source.pop_first() {
+            source.extend(only);
+        }
+    }
+
+    Some(Config {
+        arch,
+        source: Some(source),
+        arch_source: Some(arch_source),
+        vcs: Some(false),
+        build_system: Some(BuildSystem::None),
+        prepare: Some(String::new()),
+        build: Some(String::new()),
+        package: Some(vec![BIN_PACKAGE.to_string()]),
+        ..Config::default()
+    })
+}
+
+/// invalid turns the message of a validator into the error of a value given by config or flags,
+/// which cannot be asked again
+fn invalid(message: String) -> AurdersError {
+    AurdersError::Failed(message.trim_end_matches('.').to_string())
 }
 
 /// get_information gets the required information about package from user and returns it
 // this should go to utils module, right? keeping this here until I am sure about that
 // utils module seems already packged. keeping it here, until I don't.
-pub fn get_information() -> Option<Information> {
-    let (source, get_template) = handle_args();
+pub fn get_information(args: &Args) -> Result<Information, AurdersError> {
+    // fields given in config or as flags are not prompted for
+    let config = match &args.config {
+        Some(path) => read_config(path).map_err(|e| {
+            AurdersError::Failed(format!("Failed to read config {}: {}", path.display(), e))
+        })?,
+        None => Config::default(),
+    };
+
+    // existing PKGBUILD comes in between, flags can still override what it says
+    let config = match &args.from_pkgbuild {
+        Some(path) => match read_pkgbuild(path) {
+            Ok(pkgbuild) => config.merge(pkgbuild),
+            Err(e) => {
+                return Err(AurdersError::Failed(format!(
+                    "Failed to read PKGBUILD {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        },
+        None => config,
+    }
+    .merge(args.fields.clone());
+
+    // prebuilt release assets only provide defaults, anything given otherwise comes first
+    let config = match bin_config(&args.bin) {
+        Some(bin) => bin.merge(config),
+        None => config,
+    };
+
+    // an existing PKGBUILD can be tweaked rather than written again from scratch
+    let (defaults, extra) = existing_defaults(args)?;
+
+    // dry run leaves the disk untouched. Packages of prebuilt release assets have no use for a
+    // tarball, the others find out early if it cannot be written.
+    let make_tarball = !args.dry_run && args.bin.is_empty();
+    if make_tarball {
+        create_directory(&args.output_dir).map_err(|e| {
+            AurdersError::Failed(format!(
+                "Failed to create directory {}: {}",
+                args.output_dir.display(),
+                e
+            ))
+        })?;
 
-    create_directory("aurders".to_string());
+        // permission of an already existing directory is not checked by create_directory
+        check_writable(&args.output_dir).map_err(|e| {
+            AurdersError::Failed(format!(
+                "Output directory {} is not writable: {}",
+                args.output_dir.display(),
+                e
+            ))
+        })?;
+    }
+
+    // maintainer rarely changes, the one of last run is remembered as default
+    let user_config = read_user_config();
+    let input_maintainer = |prompt: &str, saved: &Option<String>| match saved {
+        Some(saved) => input_string(prompt, saved),
+        None => input_string_strict(prompt),
+    };
 
-    // Create tarball first as it is required for sha256sum
-    let tarball = match create_tarball(&source) {
-        Ok(output) => {
-            println!("\nCreated tarball successfully.");
-            output
+    let maintainer_name = config.maintainer_name.map(Ok).unwrap_or_else(|| {
+        input_maintainer(
+            "Enter the name of maintainer",
+            &defaults
+                .maintainer_name
+                .clone()
+                .or(user_config.maintainer_name.clone()),
+        )
+    })?;
+    let maintainer_email = match config.maintainer_email {
+        Some(email) if validate_email(&email) => email,
+        Some(email) => {
+            return Err(AurdersError::Failed(format!(
+                "Invalid email '{}': expected the form of local@domain.tld",
+                email
+            )))
         }
-        Err(e) => {
-            eprintln!("\nFailed to generate tarball: {}.\n", e);
-            "ERRRROOORRR".to_string()
+        None => loop {
+            let email = input_maintainer(
+                "Enter the email of maintainer",
+                &defaults
+                    .maintainer_email
+                    .clone()
+                    .or(user_config.maintainer_email.clone()),
+            )?;
+
+            if validate_email(&email) {
+                break email;
+            }
+
+            eprintln!(
+                "Invalid email '{}': expected the form of local@domain.tld. Try again.",
+                email
+            );
+            fail_in_batch()?;
+        },
+    };
+    // first run saves them without asking, later ones only when told to
+    let first_run = user_config.maintainer_name.is_none() || user_config.maintainer_email.is_none();
+    if args.save_maintainer || (first_run && !args.dry_run) {
+        let user_config = UserConfig {
+            maintainer_name: Some(maintainer_name.clone()),
+            maintainer_email: Some(maintainer_email.clone()),
+        };
+        match write_user_config(&user_config) {
+            Ok(path) => info!("Saved maintainer to {}.", path.display()),
+            Err(e) => eprintln!("Failed to save maintainer: {}.", e),
+        };
+    }
+
+    // project manifest of source (e.g. Cargo.toml) only provides defaults of prompts
+    let manifest = read_manifest(&args.source);
+
+    let pkgname = match config.pkgname {
+        Some(pkgname) => {
+            for name in &pkgname {
+                validate_pkgname(name).map_err(invalid)?;
+            }
+            Ok(pkgname)
         }
+        None => input_pkgname(
+            "Enter the name of package (separate multiple names with commas or spaces for split package)",
+            &defaults
+                .pkgname
+                .as_ref()
+                .map(|pkgname| pkgname.join(" "))
+                .or(manifest.name)
+                .unwrap_or_default(),
+        ),
+    }?;
+
+    // pkgbase only matters for split packages, single package simply uses its own name
+    let pkgbase = if pkgname.len() > 1 {
+        match config.pkgbase {
+            Some(pkgbase) => validate_pkgname(&pkgbase).map_err(invalid).map(|_| pkgbase),
+            None => input_string(
+                "Enter the base name of split package",
+                defaults.pkgbase.as_ref().unwrap_or(&pkgname[0]),
+            ),
+        }?
+    } else {
+        pkgname[0].clone()
     };
 
-    let pkginfo = Information {
-        maintainer_name: input_string_strict("Enter the name of maintainer"),
-        maintainer_email: input_string_strict("Enter the email of maintainer"),
-        pkgname: input_string_strict("Enter the name of package"),
-        pkgver: input_string("Enter the version of package(default: 1.0.0)", "1.0.0"),
-        pkgrel: input_string("Enter the release number of package(default: 1)", "1"),
-        pkgdesc: input_string("Enter the description about package", ""),
-        url: input_string("Enter the url of package", ""),
-        license: input_string("Enter the license of package", ""),
-        arch: match select_arch() {
-            Some(s) => s,
-            None => {
-                println!("Architecture not selected. Using x86_64 as default.");
-                "x86_64".to_string()
+    let pkgver = match config.pkgver {
+        Some(pkgver) => validate_pkgver(&pkgver).map_err(invalid).map(|_| pkgver),
+        None => input_string_validated(
+            "Enter the version of package",
+            defaults
+                .pkgver
+                .as_deref()
+                .or(manifest.version.as_deref())
+                .unwrap_or("1.0.0"),
+            validate_pkgver,
+        ),
+    }?;
+    let pkgrel = match config.pkgrel {
+        Some(pkgrel) => validate_pkgrel(&pkgrel).map_err(invalid).map(|_| pkgrel),
+        None => input_string_validated(
+            "Enter the release number of package",
+            defaults.pkgrel.as_deref().unwrap_or("1"),
+            validate_pkgrel,
+        ),
+    }?;
+    let epoch = match config.epoch {
+        Some(epoch) => validate_epoch(&epoch).map_err(invalid).map(|_| epoch),
+        None => input_string_validated(
+            "Enter the epoch of package",
+            defaults.epoch.as_deref().unwrap_or(""),
+            validate_epoch,
+        ),
+    }?;
+    let pkgdesc = match config.pkgdesc {
+        Some(pkgdesc) => {
+            for warning in pkgdesc_warnings(&pkgdesc, &pkgname) {
+                eprintln!("Warning: {}", warning);
+            }
+            pkgdesc
+        }
+        // not blocking, user may keep the description as it is
+        None => loop {
+            let prompt = "Enter the description about package";
+            let pkgdesc = match defaults
+                .pkgdesc
+                .as_deref()
+                .or(manifest.description.as_deref())
+            {
+                Some(description) if !description.is_empty() => input_string(prompt, description)?,
+                _ => input_string_strict(prompt)?,
+            };
+            let warnings = pkgdesc_warnings(&pkgdesc, &pkgname);
+
+            for warning in &warnings {
+                print_error(&format!("Warning: {}", warning));
+            }
+            if warnings.is_empty() || batch() || input_bool("Keep it anyway?(y/N): ")? {
+                break pkgdesc;
             }
         },
-        depends: input_string("Enter the dependencies of package: ", ""),
-        makedepends: input_string("Enter the make dependencies of package: ", ""),
-        source: match get_source() {
+    };
+    let url = match config.url {
+        Some(url) => validate_url(&url).map_err(invalid).map(|_| url),
+        None => input_string_validated(
+            "Enter the url of package",
+            defaults
+                .url
+                .as_deref()
+                .or(manifest.url.as_deref())
+                .unwrap_or(""),
+            validate_url,
+        ),
+    }?;
+    let license = config.license.map(Ok).unwrap_or_else(|| {
+        let detected = defaults
+            .license
+            .clone()
+            .or_else(|| detect_license(&args.source))
+            .unwrap_or_default();
+        if tui() {
+            return tui::select_licenses(&detected);
+        }
+        input_string(
+            "Enter the license(s) of package, separated by commas or spaces (numbers of aurders --list-licenses also work)",
+            &detected,
+        )
+        .map(|licenses| resolve_licenses(&licenses))
+    })?;
+
+    // not blocking, Arch may well know licenses that aurders does not
+    for unknown in unknown_licenses(&parse_list(&license)) {
+        eprintln!(
+            "Warning: license '{}' is not a license identifier Arch recognizes.",
+            unknown
+        );
+    }
+    let arch = match config.arch {
+        Some(arch) => {
+            validate_arch(&arch.join(" ")).map_err(invalid)?;
+            arch
+        }
+        None => match select_arch()? {
             Some(s) => s,
             None => {
-                println!("Using default source.\n");
-                "$pkgname-$pkgver-$pkgrel.tar.gz".to_string()
+                let default = default_arch();
+                info!("Architecture not selected. Using {} as default.", default);
+                vec![default]
             }
         },
-        sha256sums: match get_sha256(&tarball) {
-            Some(sha256) => sha256,
-            None => "SKIP".to_string(),
+    };
+
+    // not blocking, a port of Arch may well be unknown to aurders
+    for unknown in unknown_archs(&arch) {
+        eprintln!(
+            "Warning: architecture '{}' is not one Arch Linux or its ports build for.",
+            unknown
+        );
+    }
+    // preset only provides defaults, what the user enters replaces them
+    let depends = config.depends.map(Ok).unwrap_or_else(|| {
+        input_string(
+            "Enter the dependencies of package, separated by commas or spaces(e.g. gtk3 glibc>=2.34)",
+            defaults
+                .depends
+                .as_deref()
+                .unwrap_or(args.preset.map_or("", |preset| preset.depends())),
+        )
+    })?;
+    let mut makedepends = config.makedepends.map(Ok).unwrap_or_else(|| {
+        input_string(
+            "Enter the make dependencies of package, separated by commas or spaces",
+            defaults
+                .makedepends
+                .as_deref()
+                .unwrap_or(args.preset.map_or("", |preset| preset.makedepends())),
+        )
+    })?;
+    let checkdepends = config.checkdepends.map(Ok).unwrap_or_else(|| {
+        input_string(
+            "Enter the check dependencies of package, separated by commas or spaces",
+            defaults.checkdepends.as_deref().unwrap_or(""),
+        )
+    })?;
+    // -git packages are VCS packages by convention, others can still opt in
+    let vcs = pkgname[0].ends_with("-git")
+        || config
+            .vcs
+            .or_else(|| args.preset.and_then(|preset| preset.vcs()))
+            .map(Ok)
+            .unwrap_or_else(|| input_bool("Is this a VCS package, versioned from git?(y/N): "))?;
+
+    let optdepends = config.optdepends.map(Ok).unwrap_or_else(|| {
+        input_string(
+            "Enter the optional dependencies of package, separated by semicolons(e.g. foo: does X; bar: does Y)",
+            defaults.optdepends.as_deref().unwrap_or(""),
+        )
+    })?;
+    let provides = config.provides.map(Ok).unwrap_or_else(|| {
+        input_string(
+            "Enter what the package provides, separated by commas or spaces",
+            defaults.provides.as_deref().unwrap_or(""),
+        )
+    })?;
+    let conflicts = config.conflicts.map(Ok).unwrap_or_else(|| {
+        input_string(
+            "Enter the packages conflicting with package, separated by commas or spaces",
+            defaults.conflicts.as_deref().unwrap_or(""),
+        )
+    })?;
+    let replaces = config.replaces.map(Ok).unwrap_or_else(|| {
+        input_string(
+            "Enter the packages replaced by package, separated by commas or spaces",
+            defaults.replaces.as_deref().unwrap_or(""),
+        )
+    })?;
+    let groups = config.groups.map(Ok).unwrap_or_else(|| {
+        input_string(
+            "Enter the groups package belongs to, separated by commas or spaces",
+            defaults.groups.as_deref().unwrap_or(""),
+        )
+    })?;
+    let install = config.install.map(Ok).unwrap_or_else(|| {
+        input_string(
+            "Enter the install scriptlet of package(e.g. foo.install)",
+            defaults.install.as_deref().unwrap_or(""),
+        )
+    })?;
+    let changelog = config.changelog.map(Ok).unwrap_or_else(|| {
+        input_string(
+            "Enter the changelog file of package(e.g. foo.changelog)",
+            defaults.changelog.as_deref().unwrap_or(""),
+        )
+    })?;
+    let backup = config.backup.map(Ok).unwrap_or_else(|| {
+        input_string(
+            "Enter the files to keep across upgrades, separated by commas or spaces(e.g. etc/foo.conf)",
+            defaults.backup.as_deref().unwrap_or(""),
+        )
+    })?;
+    let options = config.options.map(Ok).unwrap_or_else(|| {
+        input_string(
+            "Enter the makepkg options of package, separated by commas or spaces(e.g. !strip !debug)",
+            defaults.options.as_deref().unwrap_or(""),
+        )
+    })?;
+
+    // not blocking either, newer makepkg may have options that aurders does not know about
+    for unknown in unknown_options(&parse_list(&options)) {
+        eprintln!(
+            "Warning: option '{}' is not a known makepkg option.",
+            unknown
+        );
+    }
+
+    // VCS package cannot be built without git
+    if vcs && !parse_depends(&makedepends).contains(&"git".to_string()) {
+        makedepends = format!("{} git", makedepends).trim().to_string();
+    }
+
+    // name of the tarball aurders creates, as seen from PKGBUILD
+    let local_tarball = format!(
+        "$pkgname-$pkgver-$pkgrel.{}",
+        args.tarball_format.extension()
+    );
+
+    let (source, checksum_algorithm) = if vcs {
+        // sources are cloned fresh from git, there is nothing to checksum
+        let source = match config.source {
+            Some(source) => source,
+            None => vec![get_vcs_source(&url)?],
+        };
+        (source, ChecksumAlgorithm::Sha256)
+    } else {
+        let source = match config.source {
+            Some(source) => source,
+            None => {
+                // release archive of a known forge is a better default than the local tarball
+                let default_source = match suggest_source(&url) {
+                    Some(suggested) => suggested,
+                    None => local_tarball.clone(),
+                };
+
+                match get_source(&default_source)? {
+                    Some(s) => s,
+                    None => {
+                        info!("Using default source.\n");
+                        vec![default_source]
+                    }
+                }
+            }
+        };
+        let checksum_algorithm = config
+            .checksum_algorithm
+            .map(Ok)
+            .unwrap_or_else(select_checksum)?;
+        (source, checksum_algorithm)
+    };
+
+    // packages of a single architecture (any included) have no use for source_<arch> arrays
+    let mut source = expand_source_dirs(source)?;
+
+    let arch_source = if vcs || arch.len() < 2 {
+        Vec::new()
+    } else {
+        let arch_source = match config.arch_source {
+            Some(mut arch_source) => arch
+                .iter()
+                .filter_map(|arch| {
+                    arch_source
+                        .remove(arch)
+                        .map(|source| (arch.clone(), source))
+                })
+                .collect(),
+            None => get_arch_sources(&arch)?,
+        };
+
+        arch_source
+            .into_iter()
+            .map(|(arch, source)| {
+                Ok(ArchSource {
+                    arch,
+                    source: expand_source_dirs(source)?,
+                    checksums: Vec::new(),
+                })
+            })
+            .collect::<Result<_, AurdersError>>()?
+    };
+
+    let filenames: Vec<String> = source
+        .iter()
+        .chain(
+            arch_source
+                .iter()
+                .flat_map(|arch_source| &arch_source.source),
+        )
+        .map(|source| source_filename(source))
+        .collect();
+
+    // only archives are extracted by makepkg, nothing to ask without any
+    let noextract = config.noextract.map(Ok).unwrap_or_else(|| {
+        if filenames.iter().any(|file| is_archive(file)) {
+            input_string(
+                "Enter the sources makepkg should not extract, by file name, separated by commas or spaces",
+                defaults.noextract.as_deref().unwrap_or(""),
+            )
+        } else {
+            Ok(String::new())
+        }
+    })?;
+
+    // not blocking, the name may well use variables the source is written without
+    for file in parse_list(&noextract) {
+        if !filenames.contains(&file) {
+            eprintln!(
+                "Warning: noextract entry '{}' is not the file name of any source.",
+                file
+            );
+        }
+    }
+
+    // signatures are only verified against the keys listed, nothing to ask without any
+    let validpgpkeys = config.validpgpkeys.map(Ok).unwrap_or_else(|| {
+        if filenames.iter().any(|file| is_signature(file)) {
+            input_string(
+                "Enter the fingerprints of PGP keys signing the sources, separated by commas or spaces",
+                defaults.validpgpkeys.as_deref().unwrap_or(""),
+            )
+        } else {
+            Ok(String::new())
+        }
+    })?;
+
+    // not blocking, makepkg is the one to reject them
+    for fingerprint in parse_list(&validpgpkeys) {
+        if let Err(e) = validate_fingerprint(&fingerprint) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+
+    // systemd service is a source of its own, installed by the first package
+    let service = config
+        .service
+        .map(Ok)
+        .unwrap_or_else(|| input_bool("Does the package provide a systemd service?(y/N): "))?;
+    let unit = format!("{}.service", pkgname[0]);
+    if service && !source.contains(&unit) {
+        source.push(unit.clone());
+    }
+
+    let build_system = config
+        .build_system
+        .or_else(|| args.preset.map(|preset| preset.build_system()))
+        .map(Ok)
+        .unwrap_or_else(select_build_system)?;
+
+    // the tarball aurders creates keeps pkgrel in the name of its directory
+    let srcdir = if source.first().map(|s| s.as_str()) == Some(local_tarball.as_str()) {
+        "$pkgname-$pkgver-$pkgrel"
+    } else {
+        "$pkgname-$pkgver"
+    };
+
+    // patches are applied once, before build() which may run many times
+    let prepare = match config.prepare {
+        Some(prepare) => prepare,
+        None => match prepare_commands(&source, srcdir) {
+            Some(commands)
+                if input_bool(
+                    "Sources include patches. Add a prepare() applying them?(y/N): ",
+                )? =>
+            {
+                commands
+            }
+            _ => String::new(),
         },
     };
 
-    if get_template {
-        get_templates();
+    let build = config.build.unwrap_or_else(|| match build_system {
+        BuildSystem::None => get_build_commands(),
+        _ => build_system.build(srcdir),
+    });
+
+    // every package function needs its own body, config is only usable if it has all of them.
+    // skeleton is only of use to a single package, split packages install different things.
+    let functions = package_functions(&pkgname);
+    let package = match config.package {
+        Some(package) if package.len() == functions.len() => package,
+        _ => {
+            let package: Vec<String> = if functions.len() == 1 && build_system != BuildSystem::None
+            {
+                vec![build_system.package(srcdir)]
+            } else {
+                functions
+                    .iter()
+                    .map(|function| get_package_commands(function))
+                    .collect()
+            };
+
+            // text of a custom license is not in the licenses package, every package ships it
+            let files: Vec<String> = license_files(&args.source)
+                .iter()
+                .filter_map(|file| file.file_name()?.to_str().map(|name| name.to_string()))
+                .collect();
+            let package: Vec<String> = package
+                .into_iter()
+                .enumerate()
+                .map(
+                    |(index, body)| match (service && index == 0, body.is_empty()) {
+                        (false, _) => body,
+                        (true, true) => service_command(&unit),
+                        (true, false) => format!("{}\n    {}", body, service_command(&unit)),
+                    },
+                )
+                .collect();
+
+            match license_commands(&license, &files, srcdir) {
+                Some(commands) => {
+                    info!("\nCustom license must be installed to /usr/share/licenses/$pkgname, added to package().");
+                    package
+                        .into_iter()
+                        .map(|body| {
+                            if body.is_empty() {
+                                commands.clone()
+                            } else {
+                                format!("{}\n    {}", body, commands)
+                            }
+                        })
+                        .collect()
+                }
+                None => package,
+            }
+        }
+    };
+
+    let mut pkginfo = Information {
+        maintainer_name,
+        maintainer_email,
+        pkgbase,
+        pkgname,
+        pkgver,
+        pkgrel,
+        epoch,
+        pkgdesc,
+        url,
+        license,
+        arch,
+        depends,
+        makedepends,
+        checkdepends,
+        optdepends,
+        provides,
+        conflicts,
+        replaces,
+        groups,
+        install,
+        changelog,
+        backup,
+        options,
+        source,
+        noextract,
+        validpgpkeys,
+        checksum_algorithm,
+        checksums: Vec::new(),
+        arch_source,
+        vcs,
+        build_system,
+        prepare,
+        build,
+        package,
+        extra,
+    };
+    pkginfo.normalize();
+
+    // nothing to review when the answers come from a script rather than a person
+    if !args.yes && !batch() && io::stdin().is_terminal() {
+        review_information(&mut pkginfo)?;
+    }
+
+    // review may have blanked a field, better to stop before downloading anything
+    pkginfo
+        .validate()
+        .map_err(|e| AurdersError::Failed(format!("Invalid package information: {}", e)))?;
+
+    // unit describes the package as reviewed, and has to be on disk to be checksummed
+    if service && !args.dry_run {
+        save_service_unit(
+            &unit,
+            &service_unit(&pkginfo.pkgname[0], &pkginfo.pkgdesc),
+            &args.output_dir,
+        )?;
+    }
+
+    // tarball is named the way PKGBUILD refers to it, which is only known now
+    if make_tarball {
+        let tarball = create_tarball(
+            &args.source,
+            &args.output_dir,
+            &pkginfo.expand_variables("$pkgname-$pkgver-$pkgrel"),
+            args.tarball_format,
+            args.compression,
+            args.include_vcs,
+            &args.exclude,
+        )
+        .map_err(|e| AurdersError::Failed(format!("Failed to generate tarball: {}", e)))?;
+        info!("\nCreated tarball successfully.");
+
+        // a signature is of no use unless the tarball itself is among sources
+        let file = source_filename(&tarball);
+        if let Some(key) = &args.sign {
+            if pkginfo.tarball_source(&file).is_some() {
+                if let Some(fingerprint) = sign_tarball(&tarball, key) {
+                    pkginfo.add_signature(&file, &fingerprint);
+                }
+            } else {
+                eprintln!(
+                    "Warning: {} is not among sources, skipping signing it.",
+                    file
+                );
+            }
+        }
+    }
+
+    // sources may refer to variables like $pkgver, so checksums need the rest of information
+    pkginfo.checksums = source_checksums(&pkginfo, &args.output_dir, !args.dry_run, &args.fetch);
+    let arch_checksums =
+        arch_source_checksums(&pkginfo, &args.output_dir, !args.dry_run, &args.fetch);
+    for (arch_source, checksums) in pkginfo.arch_source.iter_mut().zip(arch_checksums) {
+        arch_source.checksums = checksums;
+    }
+
+    if let Some(path) = &args.write_config {
+        match write_config(path, &pkginfo) {
+            Ok(_) => info!("\nWrote config to {}.", path.display()),
+            Err(e) => eprintln!("Failed to write config {}: {}.", path.display(), e),
+        };
+    }
+
+    if args.get_template {
+        get_templates(&args.fetch)?;
+    }
+
+    Ok(pkginfo)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// information returns the complete information of a single package, for tests to start from
+    pub(crate) fn information() -> Information {
+        Information {
+            maintainer_name: "Jane Doe".to_string(),
+            maintainer_email: "jane@example.com".to_string(),
+            pkgbase: "foo".to_string(),
+            pkgname: vec!["foo".to_string()],
+            pkgver: "1.0.0".to_string(),
+            pkgrel: "1".to_string(),
+            pkgdesc: "A foo tool".to_string(),
+            url: "https://example.com/foo".to_string(),
+            license: "MIT".to_string(),
+            arch: vec!["x86_64".to_string()],
+            depends: "glibc".to_string(),
+            makedepends: "cmake".to_string(),
+            source: vec!["foo-$pkgver.tar.gz".to_string()],
+            checksums: vec!["abc123".to_string()],
+            build: "make".to_string(),
+            package: vec!["make DESTDIR=\"$pkgdir\" install".to_string()],
+            ..Information::default()
+        }
+    }
+
+    #[test]
+    fn json_has_every_field() {
+        let pkginfo = Information {
+            pkgbase: "foo".to_string(),
+            pkgname: vec!["foo".to_string(), "foo-docs".to_string()],
+            pkgver: "1.0.0".to_string(),
+            arch: vec!["x86_64".to_string(), "aarch64".to_string()],
+            source: vec!["foo-$pkgver.tar.gz".to_string()],
+            checksums: vec!["abc".to_string()],
+            arch_source: vec![ArchSource {
+                arch: "aarch64".to_string(),
+                source: vec!["foo-aarch64.tar.gz".to_string()],
+                checksums: vec!["def".to_string()],
+            }],
+            package: vec![String::new(), String::new()],
+            vcs: true,
+            ..Information::default()
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&pkginfo.to_json().unwrap()).unwrap();
+
+        assert_eq!(json["pkgbase"], "foo");
+        assert_eq!(json["pkgname"][1], "foo-docs");
+        assert_eq!(json["arch"][1], "aarch64");
+        assert_eq!(json["source"][0], "foo-$pkgver.tar.gz");
+        assert_eq!(json["checksums"][0], "abc");
+        assert_eq!(json["arch_source"][0]["arch"], "aarch64");
+        assert_eq!(json["arch_source"][0]["checksums"][0], "def");
+        assert_eq!(json["vcs"], true);
+        assert!(json["extra"].is_array());
+    }
+
+    #[test]
+    fn validation_rejects_blank_pkgver() {
+        let mut pkginfo = information();
+        pkginfo.pkgver = String::new();
+
+        let error = pkginfo.validate().unwrap_err();
+        assert!(error.contains("pkgver"));
+    }
+
+    #[test]
+    fn validation_lists_every_missing_field() {
+        let mut pkginfo = information();
+        pkginfo.pkgver = String::new();
+        pkginfo.pkgdesc = "  ".to_string();
+        pkginfo.arch = Vec::new();
+
+        let error = pkginfo.validate().unwrap_err();
+        assert!(error.ends_with("pkgver, pkgdesc, arch"));
+        assert!(information().validate().is_ok());
+    }
+
+    #[test]
+    fn signature_follows_the_tarball_it_signs() {
+        let mut pkginfo = Information {
+            source: vec![
+                "$pkgname-$pkgver-$pkgrel.tar.gz".to_string(),
+                "foo.patch".to_string(),
+            ],
+            validpgpkeys: "ABCDEF".to_string(),
+            ..information()
+        };
+
+        pkginfo.add_signature("foo-1.0.0-1.tar.gz", "0123456789");
+
+        assert_eq!(
+            pkginfo.source,
+            [
+                "$pkgname-$pkgver-$pkgrel.tar.gz",
+                "$pkgname-$pkgver-$pkgrel.tar.gz.sig",
+                "foo.patch"
+            ]
+        );
+        assert_eq!(pkginfo.validpgpkeys, "ABCDEF 0123456789");
+    }
+
+    #[test]
+    fn tarball_not_among_sources_is_not_found() {
+        let pkginfo = Information {
+            source: vec!["https://example.com/foo-$pkgver.tar.gz".to_string()],
+            ..information()
+        };
+
+        assert_eq!(pkginfo.tarball_source("foo-1.0.0-1.tar.gz"), None);
+        assert_eq!(pkginfo.tarball_source("foo-1.0.0.tar.gz"), Some(0));
+    }
+
+    #[test]
+    fn given_pkgver_with_shell_characters_is_rejected() {
+        let result = validate_pkgver("1.0;touch x").map_err(invalid);
+
+        match result {
+            Err(AurdersError::Failed(message)) => {
+                assert!(message.starts_with("Invalid version '1.0;touch x'"));
+                assert!(!message.ends_with('.'));
+            }
+            _ => panic!("pkgver with ';' was accepted"),
+        }
+    }
+
+    #[test]
+    fn local_files_leave_out_downloads() {
+        let pkginfo = Information {
+            source: vec![
+                "$pkgname-$pkgver-$pkgrel.tar.gz".to_string(),
+                "https://example.com/foo-$pkgver.tar.gz".to_string(),
+                "git+https://example.com/foo.git".to_string(),
+                "foo.service".to_string(),
+            ],
+            arch_source: vec![ArchSource {
+                arch: "aarch64".to_string(),
+                source: vec!["foo.service".to_string(), "aarch64.patch".to_string()],
+                checksums: Vec::new(),
+            }],
+            install: "foo.install".to_string(),
+            ..information()
+        };
+
+        assert_eq!(
+            pkginfo.local_files(),
+            [
+                "foo.install",
+                "foo-1.0.0-1.tar.gz",
+                "foo.service",
+                "aarch64.patch"
+            ]
+        );
     }
 
-    return Some(pkginfo);
+    #[test]
+    fn normalize_cleans_up_messy_lists() {
+        let mut pkginfo = Information {
+            depends: "gtk3 ,  glib2 ,cairo".to_string(),
+            license: " MIT,Apache-2.0 ".to_string(),
+            ..information()
+        };
+
+        pkginfo.normalize();
+
+        assert_eq!(pkginfo.depends, "gtk3 glib2 cairo");
+        assert_eq!(pkginfo.license, "MIT Apache-2.0");
+        assert_eq!(
+            parse_list("gtk3 ,  glib2 ,cairo"),
+            ["gtk3", "glib2", "cairo"]
+        );
+
+        let pkgbuild =
+            crate::pkgbuild::generate_pkgbuild(&pkginfo, &crate::templates::Templates::default())
+                .unwrap();
+        assert!(pkgbuild.contains("\ndepends=('gtk3' 'glib2' 'cairo')\n"));
+    }
 }
@@ -1,84 +1,265 @@
 //! srcinfo module handles the generation of srcinfo
-use crate::utils::dead;
+use log::info;
+
+use crate::error::AurdersError;
+use crate::templates::Templates;
+use crate::utils::{
+    fill_lines, make_way_for, optional_value, parse_backup, parse_depends, parse_list,
+    parse_optdepends, unknown_placeholders,
+};
 use crate::Information;
 
-use std::fs::{self, File};
+use std::error::Error;
+use std::fs::File;
 use std::io::Write;
+use std::path::Path;
+
+/// PLACEHOLDERS are the placeholders generate_srcinfo knows how to fill in SRCINFO template
+const PLACEHOLDERS: &[&str] = &[
+    "pkgbase",
+    "pkgdesc",
+    "pkgver",
+    "pkgrel",
+    "epoch",
+    "pkgurl",
+    "arch",
+    "license",
+    "depends",
+    "makedepends",
+    "checkdepends",
+    "optdepends",
+    "provides",
+    "conflicts",
+    "replaces",
+    "backup",
+    "options",
+    "install",
+    "changelog",
+    "groups",
+    "noextract",
+    "validpgpkeys",
+    "source",
+    "checksum_array",
+    "checksums",
+    "arch_sources",
+    "pkgname",
+];
+
+/// generate_srcinfo generates and returns the SRCINFO from the SRCINFO template of templates
+pub fn generate_srcinfo(
+    pkginfo: &Information,
+    templates: &Templates,
+) -> Result<String, Box<dyn Error>> {
+    if pkginfo.pkgname.is_empty() {
+        return Err("package has no name".into());
+    }
+    pkginfo.validate()?;
+
+    // left in place, an unknown placeholder would silently end up in the .SRCINFO
+    let unknown = unknown_placeholders(
+        &templates.srcinfo,
+        &templates.known_placeholders(PLACEHOLDERS),
+    );
+    if !unknown.is_empty() {
+        return Err(format!("unknown placeholders in template: {}", unknown.join(", ")).into());
+    }
+
+    // .SRCINFO must carry the same sources as PKGBUILD, with their variables expanded
+    let source: Vec<String> = pkginfo
+        .source
+        .iter()
+        .map(|source| pkginfo.expand_variables(source))
+        .collect();
+
+    // makepkg lists every source_<arch> followed by its checksums
+    let arch_sources: Vec<String> = pkginfo
+        .arch_source
+        .iter()
+        .flat_map(|arch_source| {
+            let arch = &arch_source.arch;
+            let source = arch_source
+                .source
+                .iter()
+                .map(|source| format!("source_{} = {}", arch, pkginfo.expand_variables(source)));
+            let checksums = arch_source.paired_checksums().into_iter().map(|checksum| {
+                format!(
+                    "{}_{} = {}",
+                    pkginfo.checksum_algorithm.array(),
+                    arch,
+                    checksum
+                )
+            });
+            source.chain(checksums).collect::<Vec<String>>()
+        })
+        .collect();
+
+    // every split package gets a section of its own, separated by a blank line
+    let pkgname = pkginfo.pkgname.join("\n\npkgname = ");
+
+    // optional lines are filled first, they are dropped from template when left blank
+    let mut output = fill_lines(
+        &templates.srcinfo,
+        "{epoch}",
+        &optional_value(&pkginfo.epoch),
+    );
+    // version of VCS package is only known once pkgver() runs
+    if pkginfo.vcs {
+        output = fill_lines(&output, "{pkgver}", &Vec::new());
+    }
+    output = fill_lines(&output, "{source}", &source);
+    // makepkg pairs checksums with sources by position, so there must be one for every source
+    output = fill_lines(&output, "{checksums}", &pkginfo.paired_checksums());
+    output = fill_lines(&output, "{arch_sources}", &arch_sources);
+    output = fill_lines(&output, "{arch}", &pkginfo.arch);
+    output = fill_lines(&output, "{license}", &parse_list(&pkginfo.license));
+    output = fill_lines(&output, "{depends}", &parse_depends(&pkginfo.depends));
+    output = fill_lines(
+        &output,
+        "{makedepends}",
+        &parse_depends(&pkginfo.makedepends),
+    );
+    output = fill_lines(
+        &output,
+        "{checkdepends}",
+        &parse_depends(&pkginfo.checkdepends),
+    );
+    output = fill_lines(
+        &output,
+        "{optdepends}",
+        &parse_optdepends(&pkginfo.optdepends),
+    );
+    output = fill_lines(&output, "{provides}", &parse_depends(&pkginfo.provides));
+    output = fill_lines(&output, "{conflicts}", &parse_depends(&pkginfo.conflicts));
+    output = fill_lines(&output, "{replaces}", &parse_depends(&pkginfo.replaces));
+    output = fill_lines(&output, "{backup}", &parse_backup(&pkginfo.backup));
+    output = fill_lines(&output, "{options}", &parse_list(&pkginfo.options));
+    output = fill_lines(&output, "{install}", &optional_value(&pkginfo.install));
+    output = fill_lines(&output, "{changelog}", &optional_value(&pkginfo.changelog));
+    output = fill_lines(&output, "{groups}", &parse_list(&pkginfo.groups));
+    output = fill_lines(
+        &output,
+        "{validpgpkeys}",
+        &parse_list(&pkginfo.validpgpkeys),
+    );
+    output = fill_lines(
+        &output,
+        "{noextract}",
+        &parse_list(&pkginfo.noextract)
+            .iter()
+            .map(|file| pkginfo.expand_variables(file))
+            .collect::<Vec<String>>(),
+    );
 
-/// generate_srcinfo generates and returns the SRCINFO
-pub fn generate_srcinfo(pkginfo: &Information) {
-    let template = get_template();
-    let srcinfo: String;
-    let source = format!(
-        "{}-{}-{}.tar.gz",
-        &pkginfo.pkgname, &pkginfo.pkgver, &pkginfo.pkgrel
+    let srcinfo = templates.fill_placeholders(
+        &output,
+        &[
+            ("pkgbase", &pkginfo.pkgbase),
+            ("pkgdesc", &pkginfo.pkgdesc),
+            ("pkgver", &pkginfo.pkgver),
+            ("pkgrel", &pkginfo.pkgrel),
+            ("pkgurl", &pkginfo.url),
+            ("checksum_array", pkginfo.checksum_algorithm.array()),
+            ("pkgname", &pkgname),
+        ],
     );
 
-    match template {
-        Ok(output) => {
-            println!("\nGot SRCINFO template.");
-            srcinfo = output
-                .replace("{pkgbase}", &pkginfo.pkgname)
-                .replace("{pkgdesc}", &pkginfo.pkgdesc)
-                .replace("{pkgver}", &pkginfo.pkgver)
-                .replace("{pkgrel}", &pkginfo.pkgrel)
-                .replace("{pkgurl}", &pkginfo.url)
-                .replace("{arch}", &pkginfo.arch)
-                .replace("{license}", &pkginfo.license)
-                .replace("{makedepends}", &pkginfo.makedepends)
-                .replace("{source}", &source)
-                .replace("{sha256sums}", &pkginfo.sha256sums)
-                .replace("{pkgname}", &pkginfo.pkgname);
-
-            save_srcinfo(&srcinfo);
-        }
-        Err(e) => {
-            eprintln!("Failed to generate SRCINFO: {}.", e);
-            dead();
-        }
-    };
+    Ok(srcinfo)
 }
 
-/// get_template retrieves and returns the contents of templates/SRCINFO
-fn get_template() -> std::io::Result<String> {
-    let contents_vec = match fs::read("templates/SRCINFO") {
-        Ok(output) => output,
-        Err(e) => {
-            eprintln!("Failed to read templates/SRCINFO: {}.", e);
-            dead();
-            return Err(e);
-        }
-    };
-
-    let contents = match String::from_utf8(contents_vec) {
-        Ok(output) => output,
-        Err(e) => {
-            eprintln!("Failed to convert Vec<u8> to String: {}.", e);
-            dead();
-            "ERRROOORRR".to_string()
-        }
-    };
-
-    Ok(contents)
+/// save_srcinfo is a helper function to save .SRCINFO to output_dir, asking what to do with an
+/// existing one unless forced
+pub fn save_srcinfo(srcinfo: &String, output_dir: &Path, force: bool) -> Result<(), AurdersError> {
+    let path = output_dir.join(".SRCINFO");
+
+    make_way_for(&path, force)?;
+
+    let mut file = File::create(&path)
+        .map_err(|e| AurdersError::Failed(format!("Failed to create new .SRCINFO: {}", e)))?;
+    file.write_all(srcinfo.as_bytes())
+        .map_err(|e| AurdersError::Failed(format!("Failed to write to .SRCINFO: {}", e)))?;
+    info!("Saved .SRCINFO to disk successfully.");
+
+    Ok(())
 }
 
-/// save_srcinfo is a helper function to save .SRCINFO to disk
-fn save_srcinfo(srcinfo: &String) {
-    // create_new because it creates new file in read-write mode; error if the file exists
-    // and making sure that possibly existing SRCINFO does not get overwritten
-    let file_result = File::create_new("aurders/.SRCINFO");
-
-    match file_result {
-        Ok(mut file) => match file.write_all(srcinfo.as_bytes()) {
-            Ok(_) => println!("Saved .SRCINFO to disk successfully."),
-            Err(e) => {
-                eprintln!("Failed to write to .SRCINFO: {}.", e);
-                dead();
-            }
-        },
-        Err(e) => {
-            eprintln!("Failed to create new .SRCINFO: {}.", e);
-            dead();
-        }
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::tests::information;
+
+    #[test]
+    fn pairs_sources_with_checksums() {
+        let mut pkginfo = information();
+        pkginfo.source = vec![
+            "foo-$pkgver.tar.gz".to_string(),
+            "git+https://example.com/foo.git".to_string(),
+        ];
+        pkginfo.checksums = vec!["abc123".to_string()];
+
+        let srcinfo = generate_srcinfo(&pkginfo, &Templates::default()).unwrap();
+
+        assert!(srcinfo
+            .contains("\tsource = foo-1.0.0.tar.gz\n\tsource = git+https://example.com/foo.git\n"));
+        assert!(srcinfo.contains("\tsha256sums = abc123\n\tsha256sums = SKIP\n"));
+        assert_eq!(
+            srcinfo.matches("\tsource = ").count(),
+            srcinfo.matches("\tsha256sums = ").count()
+        );
+    }
+
+    #[test]
+    fn keeps_renamed_source() {
+        let mut pkginfo = information();
+        pkginfo.source = vec!["foo-$pkgver.tar.gz::https://example.com/download?id=1".to_string()];
+
+        let srcinfo = generate_srcinfo(&pkginfo, &Templates::default()).unwrap();
+
+        assert!(
+            srcinfo.contains("\tsource = foo-1.0.0.tar.gz::https://example.com/download?id=1\n")
+        );
+        assert!(srcinfo.contains("\tsha256sums = abc123\n"));
+    }
+
+    #[test]
+    fn saves_srcinfo_to_output_dir() {
+        let dir = tempfile::tempdir().unwrap();
+
+        save_srcinfo(&"pkgbase = foo\n".to_string(), dir.path(), false).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(".SRCINFO")).unwrap(),
+            "pkgbase = foo\n"
+        );
+    }
+
+    #[test]
+    fn agrees_with_pkgbuild() {
+        let mut pkginfo = information();
+        pkginfo.pkgbase = "foo".to_string();
+        pkginfo.pkgname = vec!["foo".to_string(), "foo-docs".to_string()];
+        pkginfo.package = vec!["make install".to_string(), "make docs".to_string()];
+        pkginfo.source = vec!["foo-$pkgver.tar.gz".to_string(), "fix.patch".to_string()];
+        pkginfo.checksums = vec!["abc123".to_string(), "def456".to_string()];
+        let templates = Templates::default();
+
+        let pkgbuild = crate::pkgbuild::generate_pkgbuild(&pkginfo, &templates).unwrap();
+        let srcinfo = generate_srcinfo(&pkginfo, &templates).unwrap();
+        let values = |key: &str| -> Vec<String> {
+            srcinfo
+                .lines()
+                .filter_map(|line| line.trim_start().strip_prefix(&format!("{} = ", key)))
+                .map(|value| value.to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            crate::pkgbuild::parse_array(&pkgbuild, "pkgname").unwrap(),
+            values("pkgname")
+        );
+        assert_eq!(
+            crate::pkgbuild::parse_array(&pkgbuild, "sha256sums").unwrap(),
+            values("sha256sums")
+        );
+        assert_eq!(values("source"), ["foo-1.0.0.tar.gz", "fix.patch"]);
+    }
 }
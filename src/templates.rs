@@ -0,0 +1,77 @@
+//! templates module provides the templates PKGBUILD and .SRCINFO are generated from
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::utils::fill_placeholders;
+
+/// Templates stores the contents of PKGBUILD and SRCINFO templates
+pub struct Templates {
+    pub pkgbuild: String,
+    pub srcinfo: String,
+    /// vars are the values of custom {name} placeholders, by their names. Built-in placeholders
+    /// take precedence over them.
+    pub vars: Vec<(String, String)>,
+}
+
+impl Default for Templates {
+    /// default returns the templates built into aurders
+    fn default() -> Templates {
+        Templates {
+            pkgbuild: include_str!("../templates/PKGBUILD").to_string(),
+            srcinfo: include_str!("../templates/SRCINFO").to_string(),
+            vars: Vec::new(),
+        }
+    }
+}
+
+impl Templates {
+    /// from_dir reads the PKGBUILD and SRCINFO templates inside dir, both of which are required
+    pub fn from_dir(dir: &Path) -> io::Result<Templates> {
+        Ok(Templates {
+            pkgbuild: read_template(dir, "PKGBUILD")?,
+            srcinfo: read_template(dir, "SRCINFO")?,
+            vars: Vec::new(),
+        })
+    }
+
+    /// known_placeholders returns builtin, the placeholders aurders fills, along with the names of
+    /// vars
+    pub fn known_placeholders<'a>(&'a self, builtin: &[&'a str]) -> Vec<&'a str> {
+        let mut known = builtin.to_vec();
+        known.extend(self.vars.iter().map(|(name, _)| name.as_str()));
+        known
+    }
+
+    /// fill_placeholders substitutes the placeholders of template with values, followed by vars
+    pub fn fill_placeholders(&self, template: &str, values: &[(&str, &str)]) -> String {
+        let mut values = values.to_vec();
+        values.extend(
+            self.vars
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        );
+
+        fill_placeholders(template, &values)
+    }
+
+    /// to_dir writes the templates as PKGBUILD and SRCINFO inside dir, creating it if needed
+    pub fn to_dir(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("PKGBUILD"), &self.pkgbuild)?;
+        fs::write(dir.join("SRCINFO"), &self.srcinfo)
+    }
+}
+
+/// read_template reads template name inside dir, naming the file in the error when it is missing
+/// or unreadable
+fn read_template(dir: &Path, name: &str) -> io::Result<String> {
+    let path = dir.join(name);
+
+    fs::read_to_string(&path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => {
+            io::Error::new(e.kind(), format!("template {} is missing", path.display()))
+        }
+        _ => io::Error::new(e.kind(), format!("{}: {}", path.display(), e)),
+    })
+}
@@ -0,0 +1,194 @@
+//! tui module provides the select menus, confirmations and inline validated inputs of --tui, which
+//! stand in for the plain prompts when aurders is used from a terminal
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{Confirm, Input, MultiSelect, Select};
+
+use crate::build_system::BuildSystem;
+use crate::checksum::ChecksumAlgorithm;
+use crate::error::AurdersError;
+use crate::utils::{default_arch, parse_list, print_error, validate_arch, COMMON_LICENSES};
+
+/// ARCHS are the architectures select_arch offers, the same ones as the plain menu
+const ARCHS: [&str; 5] = ["x86_64", "i686", "any", "aarch64", "armv7h"];
+
+/// theme returns the theme every widget is drawn with
+fn theme() -> ColorfulTheme {
+    Default::default()
+}
+
+/// interacted returns the answer of a widget, or an error when the terminal could not be used
+fn interacted<T>(result: dialoguer::Result<T>) -> Result<T, AurdersError> {
+    result.map_err(|e| AurdersError::Failed(format!("Unable to take input: {}", e)))
+}
+
+/// input gets a line of text shown along with default, which is taken when it is left blank, and
+/// asks again with the error of validate until it accepts the trimmed text
+pub fn input(
+    prompt: &str,
+    default: &str,
+    validate: fn(&str) -> Result<(), String>,
+) -> Result<String, AurdersError> {
+    let theme = theme();
+    let mut input = Input::<String>::with_theme(&theme)
+        .with_prompt(prompt)
+        .allow_empty(true)
+        .validate_with(move |value: &String| validate(value.trim()));
+
+    if !default.is_empty() {
+        input = input.default(default.to_string());
+    }
+
+    Ok(interacted(input.interact_text())?.trim().to_string())
+}
+
+/// confirm asks a yes/no question, prompt being one of the plain prompts ending with (y/N): and the
+/// like, which only tells the default
+pub fn confirm(prompt: &str) -> Result<bool, AurdersError> {
+    let question = prompt.trim().trim_end_matches(':').trim_end();
+    let default = question.ends_with("(Y/n)");
+    let question = ["(y/N)", "(y/n)", "(Y/n)"]
+        .iter()
+        .fold(question, |question, suffix| {
+            question.trim_end_matches(suffix)
+        });
+
+    let theme = theme();
+    interacted(
+        Confirm::with_theme(&theme)
+            .with_prompt(question)
+            .default(default)
+            .interact(),
+    )
+}
+
+/// select_arch lets user pick the target architectures of package from ARCHS, the one of this
+/// machine picked beforehand, with others entered as text
+pub fn select_arch() -> Result<Vec<String>, AurdersError> {
+    let default = default_arch();
+    let mut items: Vec<&str> = ARCHS.to_vec();
+    if !items.contains(&default.as_str()) {
+        items.push(&default);
+    }
+    items.push("other (enter manually)");
+
+    let defaults: Vec<bool> = items.iter().map(|item| *item == default).collect();
+    let theme = theme();
+
+    loop {
+        let chosen = interacted(
+            MultiSelect::with_theme(&theme)
+                .with_prompt("Select the target architecture(s) for your package (space to toggle)")
+                .items(&items)
+                .defaults(&defaults)
+                .interact(),
+        )?;
+
+        let mut archs: Vec<String> = Vec::new();
+        for index in chosen {
+            if index == items.len() - 1 {
+                archs.extend(parse_list(&input(
+                    "Enter target architecture(s)",
+                    "",
+                    validate_arch,
+                )?));
+            } else if !archs.iter().any(|arch| arch == items[index]) {
+                archs.push(items[index].to_string());
+            }
+        }
+
+        if archs.is_empty() {
+            print_error("Select at least one architecture.");
+            continue;
+        }
+
+        // any is only valid on its own
+        match validate_arch(&archs.join(" ")) {
+            Ok(_) => return Ok(archs),
+            Err(e) => print_error(&e),
+        }
+    }
+}
+
+/// select_licenses lets user pick the licenses of package among the common ones, detected ones
+/// picked beforehand, with others entered as text. Returns them separated by spaces.
+pub fn select_licenses(detected: &str) -> Result<String, AurdersError> {
+    let detected = parse_list(detected);
+    let mut items: Vec<String> = COMMON_LICENSES
+        .iter()
+        .map(|(license, note)| format!("{:<20}{}", license, note))
+        .collect();
+    items.push("other (enter manually)".to_string());
+
+    let defaults: Vec<bool> = COMMON_LICENSES
+        .iter()
+        .map(|(license, _)| detected.iter().any(|detected| detected == license))
+        .chain([false])
+        .collect();
+
+    // detected licenses that are not common ones are offered as the default of other
+    let other: Vec<String> = detected
+        .iter()
+        .filter(|detected| {
+            !COMMON_LICENSES
+                .iter()
+                .any(|(license, _)| license == detected)
+        })
+        .cloned()
+        .collect();
+
+    let theme = theme();
+    let chosen = interacted(
+        MultiSelect::with_theme(&theme)
+            .with_prompt("Select the license(s) of package (space to toggle)")
+            .items(&items)
+            .defaults(&defaults)
+            .interact(),
+    )?;
+
+    let mut licenses: Vec<String> = Vec::new();
+    for index in chosen {
+        if index == COMMON_LICENSES.len() {
+            licenses.extend(parse_list(&input(
+                "Enter the license(s) of package, separated by commas or spaces",
+                &other.join(" "),
+                |_| Ok(()),
+            )?));
+        } else {
+            licenses.push(COMMON_LICENSES[index].0.to_string());
+        }
+    }
+
+    Ok(licenses.join(" "))
+}
+
+/// select_build_system lets user pick the build system of package, none being the default
+pub fn select_build_system() -> Result<BuildSystem, AurdersError> {
+    let items = ["none", "make", "cmake", "meson", "python", "cargo"];
+    let theme = theme();
+
+    let chosen = interacted(
+        Select::with_theme(&theme)
+            .with_prompt("Select the build system of your package")
+            .items(&items)
+            .default(0)
+            .interact(),
+    )?;
+
+    Ok(BuildSystem::from_name(items[chosen]).unwrap_or(BuildSystem::None))
+}
+
+/// select_checksum lets user pick the checksum algorithm of sources, sha256 being the default
+pub fn select_checksum() -> Result<ChecksumAlgorithm, AurdersError> {
+    let items = ["sha256", "sha512", "b2"];
+    let theme = theme();
+
+    let chosen = interacted(
+        Select::with_theme(&theme)
+            .with_prompt("Select the checksum algorithm for sources")
+            .items(&items)
+            .default(0)
+            .interact(),
+    )?;
+
+    Ok(ChecksumAlgorithm::from_name(items[chosen]).unwrap_or_default())
+}
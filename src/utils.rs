@@ -1,348 +1,2628 @@
 //! utils module includes all the utlity and helper functions
-use std::fs::{self, remove_file, File};
-use std::io::{self, Cursor, ErrorKind, Write};
 use std::env;
-use std::path::{Path, PathBuf};
-use std::process::exit;
+use std::fs::{self, remove_file, File};
+use std::io::{self, BufRead, ErrorKind, IsTerminal, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{exit, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
-use reqwest;
-use sha256::try_digest;
-use tar::{Archive, Builder};
+use glob::{MatchOptions, Pattern};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{debug, info, Level, LevelFilter, Log, Metadata, Record};
+use reqwest::blocking::Client;
+use tar::{Archive, Builder, HeaderMode};
+use xz2::write::XzEncoder;
+use zstd::Encoder as ZstdEncoder;
 
-/// input_string gets user input in the form of string, trims and then returns it
-pub fn input_string(prompt: &str, default: &str) -> String {
-    let mut input = String::new();
+use crate::checksum::{compute_checksum, is_remote, source_filename, ChecksumAlgorithm};
+use crate::error::AurdersError;
+use crate::templates::Templates;
+use crate::tui;
 
-    println!("\n{}", prompt);
-    print!("> ");
-    io::stdout().flush().unwrap();
+/// BOLD, DIM, RED and CYAN are the ANSI codes prompts are colored with
+const BOLD: &str = "1";
+const DIM: &str = "2";
+const RED: &str = "31";
+const CYAN: &str = "36";
 
-    match io::stdin().read_line(&mut input) {
-        Ok(_) => (),
-        Err(e) => {
-            eprintln!("Unable to take input: {}.", e);
-            dead();
+/// Logger prints the status messages of aurders, info ones to stdout and the rest to stderr.
+/// Messages of dependencies are left out.
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level() && metadata.target().starts_with("aurders")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
         }
+
+        match record.level() {
+            Level::Info => println!("{}", record.args()),
+            _ => eprintln!("{}", record.args()),
+        };
+    }
+
+    fn flush(&self) {
+        let _ = io::stdout().flush();
+    }
+}
+
+static LOGGER: Logger = Logger;
+
+/// init_logger sets up printing of status messages, leaving out all but warnings and errors when
+/// quiet, and adding the debug ones (e.g. template substitutions) when verbose
+pub fn init_logger(quiet: bool, verbose: bool) {
+    let level = if quiet {
+        LevelFilter::Warn
+    } else if verbose {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Info
+    };
+
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// BATCH is set in batch mode, where answers are read from stdin without showing any prompt
+static BATCH: AtomicBool = AtomicBool::new(false);
+
+/// set_batch turns batch mode on or off
+pub fn set_batch(batch: bool) {
+    BATCH.store(batch, Ordering::Relaxed);
+}
+
+/// batch checks whether aurders runs in batch mode
+pub fn batch() -> bool {
+    BATCH.load(Ordering::Relaxed)
+}
+
+/// TUI tells whether prompts are shown as the widgets of tui module rather than plain text
+static TUI: AtomicBool = AtomicBool::new(false);
+
+/// set_tui turns the widgets of tui module on or off
+pub fn set_tui(tui: bool) {
+    TUI.store(tui, Ordering::Relaxed);
+}
+
+/// tui checks whether prompts are shown as the widgets of tui module
+pub fn tui() -> bool {
+    TUI.load(Ordering::Relaxed)
+}
+
+/// fail_in_batch fails after a wrong answer in batch mode, as asking again would shift every
+/// answer after it to the wrong prompt
+pub fn fail_in_batch() -> Result<(), AurdersError> {
+    if batch() {
+        return Err(AurdersError::InvalidAnswer);
+    }
+
+    Ok(())
+}
+
+/// CREATED holds the paths this run created (output directory, tarball), which are removed
+/// again when aurders aborts before the package is generated
+static CREATED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// KEEP_ON_ERROR is set when whatever was created is to be left in place after an error
+static KEEP_ON_ERROR: AtomicBool = AtomicBool::new(false);
+
+/// set_keep_on_error sets whether created paths are kept when aurders exits on an error
+pub fn set_keep_on_error(keep: bool) {
+    KEEP_ON_ERROR.store(keep, Ordering::Relaxed);
+}
+
+/// track_created records path as created by this run, to be removed if the run aborts
+pub fn track_created(path: &Path) {
+    if let Ok(mut created) = CREATED.lock() {
+        created.push(path.to_path_buf());
+    }
+}
+
+/// remove_created removes the paths created by this run so far, latest first. Nothing which
+/// existed before the run is removed.
+pub fn remove_created() {
+    let created = match CREATED.lock() {
+        Ok(mut created) => std::mem::take(&mut *created),
+        Err(_) => Vec::new(),
+    };
+
+    for path in created.iter().rev() {
+        let removed = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+
+        match removed {
+            Ok(_) => info!("Removed partially created {}.", path.display()),
+            Err(e) if e.kind() == ErrorKind::NotFound => (),
+            Err(e) => eprintln!("Failed to remove {}: {}.", path.display(), e),
+        };
+    }
+}
+
+/// keep_created keeps the paths created by this run from being removed, once the package is
+/// generated
+pub fn keep_created() {
+    if let Ok(mut created) = CREATED.lock() {
+        created.clear();
+    }
+}
+
+/// handle_interrupt makes Ctrl-C remove the paths created so far before exiting
+pub fn handle_interrupt() {
+    let result = ctrlc::set_handler(|| {
+        eprintln!("\nInterrupted.");
+        remove_created();
+        exit(130);
+    });
+
+    if let Err(e) = result {
+        eprintln!("Failed to set Ctrl-C handler: {}.", e);
+    }
+}
+
+/// read_input reads a line of user input into input like read_line. Ctrl-D at a terminal is an
+/// error, as there is nobody to answer the prompt anymore, while end of piped input leaves the
+/// remaining answers to their defaults.
+pub fn read_input(input: &mut String) -> Result<usize, AurdersError> {
+    let read = io::stdin().read_line(input).map_err(AurdersError::Input)?;
+
+    if read == 0 && !batch() && io::stdin().is_terminal() {
+        // error would be printed right after the prompt otherwise
+        eprintln!();
+        return Err(AurdersError::NoInput);
+    }
+
+    Ok(read)
+}
+
+/// print_prompt prints text of a prompt or menu, unless in batch mode where nobody reads it
+pub fn print_prompt(text: &str) {
+    if batch() {
+        return;
+    }
+
+    print!("{}", text);
+    let _ = io::stdout().flush();
+}
+
+/// use_color checks whether output to stream should be colored, that is it is a terminal and
+/// NO_COLOR is not set
+fn use_color(stream: &impl IsTerminal) -> bool {
+    stream.is_terminal() && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// paint wraps text in the ANSI code when stdout is colored
+fn paint(text: &str, code: &str) -> String {
+    if use_color(&io::stdout()) {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// print_error prints message to stderr, in red if stderr is colored
+pub fn print_error(message: &str) {
+    if use_color(&io::stderr()) {
+        eprintln!("\x1b[{}m{}\x1b[0m", RED, message);
+    } else {
+        eprintln!("{}", message);
+    }
+}
+
+/// show_prompt prints prompt, and default in brackets if any, along with the marker input is
+/// typed after
+fn show_prompt(prompt: &str, default: Option<&str>) {
+    if batch() {
+        return;
+    }
+
+    match default {
+        Some(default) => println!(
+            "\n{} {}",
+            paint(prompt, BOLD),
+            paint(&format!("[{}]", default), DIM)
+        ),
+        None => println!("\n{}", paint(prompt, BOLD)),
     };
+    print!("{} ", paint(">", CYAN));
+    io::stdout().flush().unwrap();
+}
+
+/// input_string gets user input in the form of string, trims and then returns it. default, shown
+/// along with prompt, is returned when input is left blank.
+pub fn input_string(prompt: &str, default: &str) -> Result<String, AurdersError> {
+    if tui() {
+        return tui::input(prompt, default, |_| Ok(()));
+    }
+
+    let mut input = String::new();
+    let shown_default = if default.is_empty() { "none" } else { default };
+
+    show_prompt(prompt, Some(shown_default));
+
+    read_input(&mut input)?;
 
     // remove any extra whitespaces
     input = input.trim().to_string();
 
     if input.is_empty() {
-        return default.to_string();
+        return Ok(default.to_string());
     }
 
-    input
+    Ok(input)
 }
 
 /// input_string_strict is a more strict version of input_string, which gets string input from
 /// user and returns the trimmed string
-pub fn input_string_strict(prompt: &str) -> String {
+pub fn input_string_strict(prompt: &str) -> Result<String, AurdersError> {
+    if tui() {
+        return tui::input(prompt, "", |input| match input.is_empty() {
+            true => Err("This field is not optional.".to_string()),
+            false => Ok(()),
+        });
+    }
+
+    match read_strict(prompt, io::stdin().lock())? {
+        Some(input) => Ok(input),
+        // see read_input, nobody is left at the terminal to answer
+        None if !batch() && io::stdin().is_terminal() => {
+            eprintln!();
+            Err(AurdersError::NoInput)
+        }
+        None => Err(AurdersError::Failed(
+            "No more input, this field is not optional".to_string(),
+        )),
+    }
+}
+
+/// read_strict reads lines from reader until one is not blank, asking with prompt each time, and
+/// returns it trimmed. None when reader ends before that.
+fn read_strict(prompt: &str, mut reader: impl BufRead) -> Result<Option<String>, AurdersError> {
     loop {
         let mut input = String::new();
 
-        println!("\n{}", prompt);
-        print!("> ");
-        io::stdout().flush().unwrap();
+        show_prompt(prompt, None);
 
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => (),
-            Err(e) => {
-                eprintln!("Unable to take input: {}.", e);
-                dead();
-            }
-        };
+        // asking again would read nothing forever
+        if reader.read_line(&mut input).map_err(AurdersError::Input)? == 0 {
+            return Ok(None);
+        }
 
         // remove any extra whitespaces
         input = input.trim().to_string();
 
         if !input.is_empty() {
-            return input;
+            return Ok(Some(input));
         } else {
-            eprintln!("This field is not optional. Try again.");
+            print_error("This field is not optional. Try again.");
+            fail_in_batch()?;
         }
     }
 }
 
-/// input_bool gets user input in the form of string, then returns true if the input is y or Y,
-/// false otherwise
-pub fn input_bool(prompt: &str) -> bool {
-    let mut input = String::new();
-
-    println!("\n{}", prompt);
-    print!("> ");
-    io::stdout().flush().unwrap();
+/// input_string_validated gets string input from user like input_string, but asks again until
+/// the validate function accepts it
+pub fn input_string_validated(
+    prompt: &str,
+    default: &str,
+    validate: fn(&str) -> Result<(), String>,
+) -> Result<String, AurdersError> {
+    if tui() {
+        return tui::input(prompt, default, validate);
+    }
 
-    match io::stdin().read_line(&mut input) {
-        Ok(_) => (),
-        Err(e) => {
-            eprintln!("Unable to take input: {}.", e);
-            dead();
-        }
-    };
+    loop {
+        let input = input_string(prompt, default)?;
 
-    match input.trim() {
-        "y" | "Y" | "yes" | "definitely" => return true,
-        _ => return false,
-    };
+        match validate(&input) {
+            Ok(_) => return Ok(input),
+            Err(e) => {
+                print_error(&format!("{} Try again.", e));
+                fail_in_batch()?;
+            }
+        };
+    }
 }
 
-/// get_sha256 performs sha256 digest generation and returns it
-pub fn get_sha256(tarball: &String) -> Option<String> {
-    let input = Path::new(&tarball);
-    let value_result = try_digest(input);
+/// input_list_strict gets a comma- or space-separated list from user and returns its entries,
+/// asking again until at least one entry is given
+pub fn input_list_strict(prompt: &str) -> Result<Vec<String>, AurdersError> {
+    loop {
+        let list = parse_list(&input_string_strict(prompt)?);
 
-    match value_result {
-        Ok(value) => return Some(value),
-        Err(e) => {
-            eprintln!(
-                "Failed to get sha256: {}.\nUsing 'SKIP' as default value.",
-                e
-            );
-            return None;
+        if !list.is_empty() {
+            return Ok(list);
+        } else {
+            print_error("This field is not optional. Try again.");
+            fail_in_batch()?;
         }
-    };
+    }
 }
 
-/// create_tarball creates tarball of given source and returns the name of tarball
-pub fn create_tarball(source: &PathBuf) -> Result<String, std::io::Error> {
-    let source_file = match source.file_name() {
-        Some(name) => match name.to_str() {
-            Some(name_str) => name_str,
-            None => {
-                eprintln!("Failed to convert: &OsStr -> &str");
-                dead();
-                &"ERRROOORRR".to_string()
+/// input_pkgname gets the names of package from user, defaulting to default unless it is empty,
+/// and asks again until every one of them is valid. Names only invalid for their uppercase
+/// letters can be lowercased instead.
+pub fn input_pkgname(prompt: &str, default: &str) -> Result<Vec<String>, AurdersError> {
+    'prompt: loop {
+        let names = if default.is_empty() {
+            input_list_strict(prompt)?
+        } else {
+            parse_list(&input_string(prompt, default)?)
+        };
+
+        let mut valid = Vec::new();
+        for name in names {
+            if let Err(e) = validate_pkgname(&name) {
+                print_error(&e);
+
+                let lowercase = name.to_lowercase();
+                if lowercase == name
+                    || validate_pkgname(&lowercase).is_err()
+                    || !input_bool(&format!("Use '{}' instead?(y/N): ", lowercase))?
+                {
+                    fail_in_batch()?;
+                    continue 'prompt;
+                }
+                valid.push(lowercase);
+            } else {
+                valid.push(name);
             }
-        },
-        None => {
-            eprintln!("Failed to extract filename from source.");
-            dead();
-            &"ERRROOORRR".to_string()
         }
-    };
 
-    let tarball_name = format!("aurders/{}.tar.gz", source_file);
+        return Ok(valid);
+    }
+}
+
+/// input_bool gets user input in the form of string, then returns true if the input is y or Y,
+/// false otherwise
+pub fn input_bool(prompt: &str) -> Result<bool, AurdersError> {
+    if tui() {
+        return tui::confirm(prompt);
+    }
+
+    let mut input = String::new();
 
-    let tar_gz = File::create(&tarball_name)?;
+    show_prompt(prompt, None);
 
-    let enc = GzEncoder::new(tar_gz, Compression::default());
-    let mut tar = Builder::new(enc);
+    read_input(&mut input)?;
 
-    match tar.append_dir_all(&source_file, &source) {
-        Ok(_) => (),
-        Err(e) => {
-            eprintln!("Failed to append source to tarball. Make sure source is a directory.");
-            eprintln!("Got: {}.", e);
-            dead();
-            return Err(e);
+    Ok(matches!(input.trim(), "y" | "Y" | "yes" | "definitely"))
+}
+
+/// TarballFormat is the compression of the tarball created from source directory
+#[derive(Clone, Copy, Default)]
+pub enum TarballFormat {
+    #[default]
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl TarballFormat {
+    /// from_name returns the format of given name (gzip, xz or zstd)
+    pub fn from_name(name: &str) -> Option<TarballFormat> {
+        match name {
+            "gzip" => Some(TarballFormat::Gzip),
+            "xz" => Some(TarballFormat::Xz),
+            "zstd" => Some(TarballFormat::Zstd),
+            _ => None,
         }
-    };
+    }
 
-    Ok(tarball_name)
+    /// extension returns the file extension of tarballs in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TarballFormat::Gzip => "tar.gz",
+            TarballFormat::Xz => "tar.xz",
+            TarballFormat::Zstd => "tar.zst",
+        }
+    }
+
+    /// level returns the level of this format standing for compression level from 0 to 9, gzip
+    /// and xz using the same scale. zstd goes from 1 to 22, so fast is its 1, default its own
+    /// default of 3 and best its 22.
+    pub fn level(&self, compression: u32) -> u32 {
+        match self {
+            TarballFormat::Gzip | TarballFormat::Xz => compression.min(9),
+            TarballFormat::Zstd => ZSTD_LEVELS[compression.min(9) as usize],
+        }
+    }
 }
 
-/// select_arch functions allows user to choose from architectures easily
-pub fn select_arch() -> Option<String> {
-    println!("\nSelect the target architecture for your package:");
-    io::stdout().flush().unwrap(); // Flush the output correctly
+/// ZSTD_LEVELS are the zstd levels of compression levels from 0 to 9
+const ZSTD_LEVELS: [u32; 10] = [1, 1, 2, 2, 3, 3, 3, 9, 15, 22];
 
-    loop {
-        print!("  [1] x86_64(Default)    [2] i686    [3] any    [4] Enter manually\n> ");
-        io::stdout().flush().unwrap();
-        let mut input = String::new();
+/// create_tarball creates tarball of given source inside output_dir, compressed in format at the
+/// given level, and returns the path of tarball. Both the tarball and the directory it unpacks to
+/// are named name, the way PKGBUILD refers to them. Files keep their permissions, and symlinks are
+/// stored as symlinks rather than the files they point to, so the tarball unpacks to the same
+/// tree as source. VCS metadata (.git, .svn, ...) is left out unless include_vcs, as is anything
+/// matching the exclude patterns or those in .aurdersignore of source.
+pub fn create_tarball(
+    source: &Path,
+    output_dir: &Path,
+    name: &str,
+    format: TarballFormat,
+    compression: u32,
+    include_vcs: bool,
+    exclude: &[String],
+) -> Result<String, AurdersError> {
+    let mut patterns = exclude.to_vec();
+    match fs::read_to_string(source.join(".aurdersignore")) {
+        Ok(contents) => patterns.extend(
+            contents
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| line.to_string()),
+        ),
+        Err(e) if e.kind() == ErrorKind::NotFound => (),
+        Err(e) => return Err(AurdersError::Io(e)),
+    }
+    let filter = TarballFilter::new(include_vcs, &patterns)?;
 
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => (),
-            Err(e) => eprintln!("Invalid input: {}", e),
-        };
+    let tarball_name = output_dir
+        .join(format!("{}.{}", name, format.extension()))
+        .display()
+        .to_string();
 
-        let arch: u8 = match input.trim().parse() {
-            Ok(ip) => ip,
-            Err(_) => 1, // x86_64 as default arch
-        };
+    let file = File::create(&tarball_name)?;
+    track_created(Path::new(&tarball_name));
 
-        match arch {
-            1 => return Some("x86_64".to_string()),
-            2 => return Some("i686".to_string()),
-            3 => return Some("any".to_string()),
-            4 => {
-                let mut arch = String::new();
-                print!("Enter target architecture: ");
-                io::stdout().flush().unwrap();
-
-                io::stdin()
-                    .read_line(&mut arch)
-                    .expect("Failed to get input.");
-
-                // nasty, but works
-                return Some(arch.trim().replace(" ", "' '").to_string());
-            }
-            _ => {
-                eprintln!("Invalid input. Try again");
-            }
-        };
+    // half written tarball is of no use to anyone
+    if let Err(e) = write_tarball(file, format, compression, name, source, &filter) {
+        let _ = remove_file(&tarball_name);
+        return Err(e);
     }
+
+    Ok(tarball_name)
 }
 
-/// create_directory creates directory according to given path
-pub fn create_directory(path: String) {
-    match fs::create_dir(&path) {
-        Ok(_) => println!("Created directory {}.", &path),
-        Err(e) => match e.kind() {
-            ErrorKind::AlreadyExists => println!("Directory already exists."),
-            ErrorKind::PermissionDenied => {
-                eprintln!("Cannot create directory, permission denied");
-                dead();
-            }
-            _ => {
-                eprintln!(
-                    "Failed to create directory. Unknown error occurred.\nPath: {}.",
-                    &path
-                );
-                dead();
-            }
-        },
+/// sign_tarball makes a detached signature (tarball.sig) of tarball with the PGP key of key id key,
+/// and returns the fingerprint of key. None when gpg is not installed or signing fails, the
+/// tarball simply being left unsigned.
+pub fn sign_tarball(tarball: &str, key: &str) -> Option<String> {
+    if find_program("gpg").is_none() {
+        info!("\ngpg is not installed, skipping signing tarball.");
+        return None;
+    }
+
+    let signature = format!("{}.sig", tarball);
+    let signed = Command::new("gpg")
+        .args(["--batch", "--yes", "--local-user", key, "--output"])
+        .arg(&signature)
+        .args(["--detach-sign", tarball])
+        .status();
+    match signed {
+        Ok(status) if status.success() => track_created(Path::new(&signature)),
+        Ok(_) => {
+            eprintln!("Failed to sign {} with key {}.", tarball, key);
+            return None;
+        }
+        Err(e) => {
+            eprintln!("Failed to run gpg: {}.", e);
+            return None;
+        }
     };
+    info!("Signed tarball with key {}.", key);
+
+    // validpgpkeys only takes full fingerprints, which key id may not be
+    let output = Command::new("gpg")
+        .args(["--batch", "--with-colons", "--fingerprint", key])
+        .output()
+        .ok()?;
+    let fingerprint = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.starts_with("fpr:"))
+        .and_then(|line| line.split(':').nth(9))
+        .map(|fingerprint| fingerprint.to_string());
+
+    if fingerprint.is_none() {
+        eprintln!("Failed to find the fingerprint of key {}.", key);
+    }
+
+    fingerprint
 }
 
-/// decompress_tarball decompresses the tarball specified at tarball_path
-fn decompress_tarball(tarball_path: String) -> Result<(), std::io::Error> {
-    let tar_gz = File::open(tarball_path)?;
-    let tar = GzDecoder::new(tar_gz);
-    let mut archive = Archive::new(tar);
-    archive.unpack(".")?;
+/// write_tarball writes the tarball of source into file, compressed in format at the given level
+fn write_tarball(
+    file: File,
+    format: TarballFormat,
+    compression: u32,
+    source_file: &str,
+    source: &Path,
+    filter: &TarballFilter,
+) -> Result<(), AurdersError> {
+    let level = format.level(compression);
+
+    // encoders are finished by hand, errors of writing the last blocks would go unnoticed if left
+    // to drop
+    match format {
+        TarballFormat::Gzip => {
+            let encoder = GzEncoder::new(file, Compression::new(level));
+            append_source(encoder, source_file, source, filter)?.finish()?;
+        }
+        TarballFormat::Xz => {
+            let encoder = XzEncoder::new(file, level);
+            append_source(encoder, source_file, source, filter)?.finish()?;
+        }
+        TarballFormat::Zstd => {
+            let encoder = ZstdEncoder::new(file, level as i32)?;
+            append_source(encoder, source_file, source, filter)?.finish()?;
+        }
+    };
 
     Ok(())
 }
 
-/// fetch_data fetches the data from given url and writes to given filename
-fn fetch_data(url: String, filename: String) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Attempting to fetch {}...", filename);
-    let response = reqwest::blocking::get(url)?.bytes()?;
-    let mut file = File::create(filename)?;
-    let mut content = Cursor::new(response);
-    io::copy(&mut content, &mut file)?;
-    println!("Fetched templates successfully.");
+/// VCS_DIRS are the version control metadata directories left out of tarball
+const VCS_DIRS: &[&str] = &[".git", ".svn", ".hg", ".bzr"];
 
-    Ok(())
+/// TarballFilter decides which entries of source directory are left out of tarball
+struct TarballFilter {
+    include_vcs: bool,
+    /// exclude patterns, along with whether they only match directories
+    patterns: Vec<(Pattern, bool)>,
 }
 
-/// get_templates retrieve the template by calling fetch_data() correctly
-// not to be confused with get_template functions in {pkgbuild, srcinfo}, they retrieve local
-// templates from templates/ directory.
-pub fn get_templates() {
-    let url = "https://github.com/miteshhc/aurders/releases/download/template/templates.tar.gz";
-    let filename = "templates.tar.gz";
+impl TarballFilter {
+    /// new parses the gitignore-like exclude patterns: those ending with / only match
+    /// directories, and those without / in between match the name at any depth rather than
+    /// the path relative to source
+    fn new(include_vcs: bool, patterns: &[String]) -> Result<TarballFilter, AurdersError> {
+        let mut parsed = Vec::new();
 
-    match fetch_data(url.to_string(), filename.to_string()) {
-        Ok(_) => (),
-        Err(e) => {
-            eprintln!("Unable to fetch data: {}.", e);
-            dead();
+        for pattern in patterns {
+            let dir_only = pattern.ends_with('/');
+            let trimmed = pattern.trim_end_matches('/');
+            let trimmed = if trimmed.contains('/') {
+                trimmed.trim_start_matches('/').to_string()
+            } else {
+                format!("**/{}", trimmed)
+            };
+
+            match Pattern::new(&trimmed) {
+                Ok(parsed_pattern) => parsed.push((parsed_pattern, dir_only)),
+                Err(e) => {
+                    return Err(AurdersError::Source(format!(
+                        "Invalid exclude pattern {}: {}",
+                        pattern, e
+                    )))
+                }
+            }
         }
-    };
 
-    match decompress_tarball(filename.to_string()) {
-        Ok(_) => (),
-        Err(e) => {
-            eprintln!("Failed to decompress archive: {}.", e);
-            dead();
+        Ok(TarballFilter {
+            include_vcs,
+            patterns: parsed,
+        })
+    }
+
+    /// excludes checks whether entry at relative path inside source is left out of tarball
+    fn excludes(&self, relative: &Path, is_dir: bool) -> bool {
+        let is_vcs = relative
+            .file_name()
+            .is_some_and(|name| VCS_DIRS.iter().any(|vcs| name == *vcs));
+        if !self.include_vcs && is_vcs {
+            return true;
         }
-    };
 
-    match remove_file(filename) {
-        Ok(_) => println!("Removed file: {}.", filename),
-        Err(e) => eprintln!(
-            "Failed to remove {}: {}.\nYou might want to remove it manually.",
-            filename, e
-        ),
-    };
+        // * stays within a single component of path, as it does in .gitignore
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        self.patterns.iter().any(|(pattern, dir_only)| {
+            (is_dir || !dir_only) && pattern.matches_path_with(relative, options)
+        })
+    }
 }
 
-/// dead performs any required cleanup and exists the program abnormally
-pub fn dead() {
-    eprintln!("Exiting...");
-    exit(1);
+/// append_source writes the tar archive of source, named name inside it, to writer and returns
+/// writer back
+fn append_source<W: Write>(
+    writer: W,
+    name: &str,
+    source: &Path,
+    filter: &TarballFilter,
+) -> Result<W, AurdersError> {
+    let mut tar = Builder::new(writer);
+    // executable bits of build scripts would otherwise be at the mercy of the defaults
+    tar.mode(HeaderMode::Complete);
+    tar.follow_symlinks(false);
+
+    if let Err(e) = append_tree(&mut tar, Path::new(name), source, Path::new(""), filter) {
+        return Err(AurdersError::Source(format!(
+            "Failed to append source to tarball. Make sure source is a directory. Got: {}",
+            e
+        )));
+    }
+
+    Ok(tar.into_inner()?)
 }
 
-/// dead_probably asks the user, if they want to continue or ...
-pub fn dead_probably() {
-    let mut dead_huh = String::new();
+/// append_tree appends directory at path, relative to source, to tar as name along with
+/// everything inside it that filter does not exclude
+fn append_tree<W: Write>(
+    tar: &mut Builder<W>,
+    name: &Path,
+    path: &Path,
+    relative: &Path,
+    filter: &TarballFilter,
+) -> io::Result<()> {
+    // sorted so the archive does not depend on the order directory happens to be read in
+    let mut entries = fs::read_dir(path)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
 
-    println!("Do you still want to continue(y/N)");
-    print!("> ");
+    tar.append_dir(name, path)?;
 
-    match io::stdin().read_line(&mut dead_huh) {
-        Ok(_) => (),
-        Err(e) => {
-            eprintln!("argh, even asking for this wouldn't work??? {}", e);
-            dead();
+    for entry in entries {
+        let file_name = entry.file_name();
+        let entry_relative = relative.join(&file_name);
+        // file_type does not follow symlinks, so linked directories are stored as links
+        let is_dir = entry.file_type()?.is_dir();
+        if filter.excludes(&entry_relative, is_dir) {
+            continue;
         }
-    };
 
-    match dead_huh.trim() {
-        "y" | "Y" => dead(),
-        _ => (),
-    };
+        let entry_name = name.join(&file_name);
+        if is_dir {
+            append_tree(tar, &entry_name, &entry.path(), &entry_relative, filter)?;
+        } else {
+            tar.append_path_with_name(entry.path(), &entry_name)?;
+        }
+    }
+
+    Ok(())
 }
 
-/// get_source gets the source from user
-pub fn get_source() -> Option<String> {
-    let mut input = String::new();
+/// select_arch functions allows user to choose from architectures easily, more than one can be
+/// chosen by separating the choices with commas or spaces
+pub fn select_arch() -> Result<Option<Vec<String>>, AurdersError> {
+    if tui() {
+        return tui::select_arch().map(Some);
+    }
 
-    println!("\nDo you want to specify source(s) manually?(y/N)");
-    print!("> ");
-    io::stdout().flush().unwrap();
+    let default = default_arch();
 
-    match io::stdin().read_line(&mut input) {
-        Ok(_) => (),
-        Err(e) => {
-            eprintln!("Unable to take input: {}.", e);
-            dead();
+    print_prompt(&format!(
+        "\nSelect the target architecture(s) for your package (Default: {}, this machine):\n",
+        default
+    ));
+    io::stdout().flush().unwrap(); // Flush the output correctly
+
+    'select: loop {
+        // new choices are numbered after the existing ones, so that answers scripted for batch
+        // mode keep their meaning
+        print_prompt("  [1] x86_64    [2] i686    [3] any    [4] Enter manually    [5] aarch64    [6] armv7h\n> ");
+        io::stdout().flush().unwrap();
+        let mut input = String::new();
+
+        read_input(&mut input)?;
+
+        let choices = parse_list(&input);
+
+        if choices.is_empty() {
+            return Ok(Some(vec![default]));
         }
-    };
 
-    let input = input.trim();
+        let mut archs: Vec<String> = Vec::new();
 
-    match input {
-        "Y" | "y" => {
-            let mut source = String::new();
-            print!("\nSource > ");
-            io::stdout().flush().unwrap();
+        for choice in choices {
+            match choice.parse::<u8>() {
+                Ok(1) => archs.push("x86_64".to_string()),
+                Ok(2) => archs.push("i686".to_string()),
+                Ok(3) => archs.push("any".to_string()),
+                Ok(5) => archs.push("aarch64".to_string()),
+                Ok(6) => archs.push("armv7h".to_string()),
+                Ok(4) => {
+                    let mut arch = String::new();
+                    print_prompt("Enter target architecture(s): ");
+                    io::stdout().flush().unwrap();
 
-            match io::stdin().read_line(&mut source) {
-                Ok(_) => (),
-                Err(e) => {
-                    eprintln!("Unable to take input: {}.", e);
-                    dead();
+                    read_input(&mut arch)?;
+
+                    if let Err(e) = validate_arch(&arch) {
+                        print_error(&format!("{} Try again.", e));
+                        fail_in_batch()?;
+                        continue 'select;
+                    }
+
+                    archs.extend(parse_list(&arch));
+                }
+                _ => {
+                    eprintln!("Invalid input. Try again");
+                    fail_in_batch()?;
+                    continue 'select;
                 }
+            };
+        }
+
+        // the same architecture chosen twice would be listed twice
+        let mut unique: Vec<String> = Vec::new();
+        for arch in archs {
+            if !unique.contains(&arch) {
+                unique.push(arch);
             }
+        }
+        let archs = unique;
+
+        // any is only valid on its own, which menu choices alone can get wrong too
+        if let Err(e) = validate_arch(&archs.join(" ")) {
+            print_error(&format!("{} Try again.", e));
+            fail_in_batch()?;
+            continue 'select;
+        }
 
-            return Some(source.trim().to_string());
+        if archs.is_empty() {
+            return Ok(None);
         }
-        _ => None,
+
+        return Ok(Some(archs));
     }
 }
 
-/// get_arch returns the current architecture
-pub fn get_arch() -> String {
+/// find_program looks for the executable named name in PATH, the way which does
+pub fn find_program(name: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+
+    env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// create_directory creates directory according to given path, an already existing one is fine
+pub fn create_directory(path: &Path) -> io::Result<()> {
+    match fs::create_dir(path) {
+        Ok(_) => {
+            info!("Created directory {}.", path.display());
+            track_created(path);
+        }
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => info!("Directory already exists."),
+        Err(e) => return Err(e),
+    };
+
+    Ok(())
+}
+
+/// check_writable tells whether files can be created in dir by creating and removing one, as
+/// read-only permission bits say nothing of ownership, ACLs or read-only mounts
+pub fn check_writable(dir: &Path) -> io::Result<()> {
+    let probe = dir.join(format!(".aurders-probe-{}", std::process::id()));
+
+    File::create(&probe)?;
+    remove_file(&probe)
+}
+
+/// make_way_for decides what to do with an existing file at path before it gets written: it is
+/// overwritten when forced, otherwise user chooses to overwrite it, back it up or abort, the last
+/// of which is an error.
+pub fn make_way_for(path: &Path, force: bool) -> Result<(), AurdersError> {
+    if !path.exists() || force {
+        return Ok(());
+    }
+
+    print_prompt(&format!(
+        "\n{} already exists. What do you want to do?\n",
+        path.display()
+    ));
+
+    loop {
+        print_prompt("  [1] Overwrite    [2] Backup and write    [3] Abort(Default)\n> ");
+        io::stdout().flush().unwrap();
+        let mut input = String::new();
+
+        read_input(&mut input)?;
+
+        // abort as default, nothing gets lost
+        let choice: u8 = input.trim().parse().unwrap_or(3);
+
+        match choice {
+            1 => return Ok(()),
+            2 => {
+                let timestamp = match SystemTime::now().duration_since(UNIX_EPOCH) {
+                    Ok(duration) => duration.as_secs(),
+                    Err(_) => 0,
+                };
+                let backup = format!("{}.{}.bak", path.display(), timestamp);
+
+                match fs::rename(path, &backup) {
+                    Ok(_) => {
+                        info!("Backed up to {}.", backup);
+                        return Ok(());
+                    }
+                    Err(e) => eprintln!("Failed to backup {}: {}.", path.display(), e),
+                };
+            }
+            3 => {
+                return Err(AurdersError::Failed(format!(
+                    "Not writing {}, it already exists",
+                    path.display()
+                )))
+            }
+            _ => {
+                eprintln!("Invalid input. Try again");
+                fail_in_batch()?;
+            }
+        };
+    }
+}
+
+/// decompress_tarball decompresses the tarball specified at tarball_path into dir, refusing entries
+/// that would end up outside of it
+fn decompress_tarball(tarball_path: &Path, dir: &Path) -> Result<(), std::io::Error> {
+    let tar_gz = File::open(tarball_path)?;
+    let tar = GzDecoder::new(tar_gz);
+    let mut archive = Archive::new(tar);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+
+        // links are checked too, else a later entry could be written through them
+        let escapes = !stays_inside(&path)
+            || match entry.link_name()? {
+                Some(target) => !stays_inside(&target),
+                None => false,
+            };
+
+        if escapes {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("entry {} points outside of directory", path.display()),
+            ));
+        }
+
+        entry.unpack_in(dir)?;
+    }
+
+    Ok(())
+}
+
+/// stays_inside checks whether relative path stays inside the directory it is joined to, that is
+/// it is neither absolute nor goes up with ..
+fn stays_inside(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// FetchOptions controls how downloads are made
+pub struct FetchOptions {
+    /// times a failed download is retried
+    pub retries: u32,
+    /// how long connecting or waiting for data may take before giving up
+    pub timeout: Duration,
+    /// refuse to download anything
+    pub offline: bool,
+}
+
+/// fetch_data fetches the data from given url and writes to given filename. Failed requests are
+/// retried up to fetch.retries times, waiting twice as long before each retry starting from a
+/// second.
+pub fn fetch_data(
+    url: String,
+    filename: String,
+    fetch: &FetchOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if fetch.offline {
+        return Err(format!("Not fetching {} in offline mode", url).into());
+    }
+
+    // proxies of HTTP_PROXY, HTTPS_PROXY (and NO_PROXY) are picked up by the builder itself
+    let client = Client::builder()
+        .connect_timeout(fetch.timeout)
+        .timeout(fetch.timeout)
+        .build()?;
+    let retries = fetch.retries;
+
+    let mut attempt = 0;
+    loop {
+        if attempt == 0 {
+            info!("Attempting to fetch {}...", filename);
+        } else {
+            info!(
+                "Attempting to fetch {} (retry {} of {})...",
+                filename, attempt, retries
+            );
+        }
+
+        match download(&client, &url, &filename) {
+            Ok(_) => break,
+            Err(e) if attempt < retries => {
+                let delay = Duration::from_secs(1 << attempt.min(6));
+                eprintln!(
+                    "Failed to fetch {}: {}. Retrying in {}s.",
+                    filename,
+                    e,
+                    delay.as_secs()
+                );
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    info!("Fetched {} successfully.", filename);
+
+    Ok(())
+}
+
+/// download streams the body of url into filename, showing its progress as a bar when the length
+/// is known and as a spinner otherwise
+fn download(client: &Client, url: &str, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let response = client.get(url).send()?;
+
+    // progress is as chatty as any other status message
+    let progress = match response.content_length() {
+        _ if log::max_level() < LevelFilter::Info => ProgressBar::hidden(),
+        Some(length) => ProgressBar::new(length).with_style(
+            ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes} ({eta})")?
+                .progress_chars("=> "),
+        ),
+        None => {
+            let spinner = ProgressBar::new_spinner()
+                .with_style(ProgressStyle::with_template("{spinner} {bytes}")?);
+            spinner.enable_steady_tick(Duration::from_millis(100));
+            spinner
+        }
+    };
+
+    let mut file = File::create(filename)?;
+    let copied = io::copy(&mut progress.wrap_read(response), &mut file);
+    progress.finish_and_clear();
+    copied?;
+
+    Ok(())
+}
+
+/// get_templates retrieve the template by calling fetch_data() correctly, only needed to
+/// customize them as the default templates are built into aurders
+// not to be confused with Templates, which holds the embedded templates or the ones read from
+// --template-dir.
+pub fn get_templates(fetch: &FetchOptions) -> Result<(), AurdersError> {
+    let url = "https://github.com/miteshhc/aurders/releases/download/template/templates.tar.gz";
+    let filename = "templates.tar.gz";
+
+    if fetch.offline {
+        return use_local_templates(Path::new("templates"));
+    }
+
+    fetch_data(url.to_string(), filename.to_string(), fetch)
+        .map_err(|e| AurdersError::Failed(format!("Unable to fetch data: {}", e)))?;
+
+    // archive drives code generation, so it must match the digest published next to it
+    if !verify_templates(url, filename, fetch) {
+        if let Err(e) = remove_file(filename) {
+            eprintln!("Failed to remove {}: {}.", filename, e);
+        }
+        return Err(AurdersError::Failed(format!(
+            "Failed to verify {}",
+            filename
+        )));
+    }
+
+    decompress_tarball(Path::new(filename), Path::new("."))
+        .map_err(|e| AurdersError::Failed(format!("Failed to decompress archive: {}", e)))?;
+
+    match remove_file(filename) {
+        Ok(_) => info!("Removed file: {}.", filename),
+        Err(e) => eprintln!(
+            "Failed to remove {}: {}.\nYou might want to remove it manually.",
+            filename, e
+        ),
+    };
+
+    Ok(())
+}
+
+/// use_local_templates stands in for get_templates in offline mode, keeping the templates already
+/// in dir or writing the built-in ones there
+fn use_local_templates(dir: &Path) -> Result<(), AurdersError> {
+    if Templates::from_dir(dir).is_ok() {
+        info!("Offline, keeping the templates in {}.", dir.display());
+        return Ok(());
+    }
+
+    Templates::default().to_dir(dir).map_err(|e| {
+        AurdersError::Failed(format!(
+            "Offline and no templates in {}, failed to write built-in ones: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+    info!("Offline, wrote built-in templates to {}.", dir.display());
+
+    Ok(())
+}
+
+/// verify_templates fetches the .sha256 sidecar of the template archive at url and compares it
+/// with the sha256 of downloaded filename. Returns whether they match.
+fn verify_templates(url: &str, filename: &str, fetch: &FetchOptions) -> bool {
+    let sidecar = format!("{}.sha256", filename);
+
+    if let Err(e) = fetch_data(format!("{}.sha256", url), sidecar.clone(), fetch) {
+        eprintln!("Unable to fetch checksum of {}: {}.", filename, e);
+        return false;
+    }
+
+    let expected = fs::read_to_string(&sidecar);
+
+    if let Err(e) = remove_file(&sidecar) {
+        eprintln!(
+            "Failed to remove {}: {}.\nYou might want to remove it manually.",
+            sidecar, e
+        );
+    }
+
+    // sidecar is in the format of sha256sum, digest followed by the name of file
+    let expected = match expected {
+        Ok(contents) => match contents.split_whitespace().next() {
+            Some(digest) => digest.to_lowercase(),
+            None => {
+                eprintln!("Checksum of {} is empty.", filename);
+                return false;
+            }
+        },
+        Err(e) => {
+            eprintln!("Failed to read checksum of {}: {}.", filename, e);
+            return false;
+        }
+    };
+
+    match compute_checksum(&filename.to_string(), &ChecksumAlgorithm::Sha256) {
+        Some(actual) if actual == expected => true,
+        Some(actual) => {
+            eprintln!(
+                "Checksum mismatch for {}: expected {}, got {}. Refusing to unpack it.",
+                filename, expected, actual
+            );
+            false
+        }
+        None => false,
+    }
+}
+
+/// dead performs any required cleanup and exists the program abnormally
+pub fn dead() {
+    if !KEEP_ON_ERROR.load(Ordering::Relaxed) {
+        remove_created();
+    }
+    eprintln!("Exiting...");
+    exit(1);
+}
+
+/// dead_probably asks the user, if they want to continue or ... Returns Aborted when they answer y.
+pub fn dead_probably() -> Result<(), AurdersError> {
+    let mut dead_huh = String::new();
+
+    print_prompt("Do you still want to continue(y/N)\n");
+    print_prompt("> ");
+
+    read_input(&mut dead_huh)?;
+
+    match dead_huh.trim() {
+        "y" | "Y" => Err(AurdersError::Aborted),
+        _ => Ok(()),
+    }
+}
+
+/// get_source gets the source(s) from user, one per line until a blank line is entered. Returns
+/// None when user wants to go with the default source.
+pub fn get_source(default: &str) -> Result<Option<Vec<String>>, AurdersError> {
+    let mut input = String::new();
+
+    print_prompt(&format!("\nDefault source: {}\n", default));
+    print_prompt("Do you want to specify source(s) manually?(y/N)\n");
+    print_prompt("> ");
+    io::stdout().flush().unwrap();
+
+    read_input(&mut input)?;
+
+    let input = input.trim();
+
+    match input {
+        "Y" | "y" => {
+            print_prompt("\nEnter one source per line. [blank line to finish]\n");
+            let sources = input_sources()?;
+
+            if sources.is_empty() {
+                return Ok(None);
+            }
+
+            Ok(Some(sources))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// get_arch_sources gets the sources specific to each of arch from user, e.g. the prebuilt
+/// binaries of a -bin package. Returns the architectures given any sources along with them, none
+/// when user does not want any.
+pub fn get_arch_sources(arch: &[String]) -> Result<Vec<(String, Vec<String>)>, AurdersError> {
+    if !input_bool("\nDo you want to specify sources specific to each architecture?(y/N): ")? {
+        return Ok(Vec::new());
+    }
+
+    let mut arch_sources = Vec::new();
+    for arch in arch {
+        print_prompt(&format!(
+            "\nEnter one source of {} per line. [blank line to finish]\n",
+            arch
+        ));
+        let sources = input_sources()?;
+
+        if !sources.is_empty() {
+            arch_sources.push((arch.clone(), sources));
+        }
+    }
+
+    Ok(arch_sources)
+}
+
+/// input_sources reads sources from user, one per line until a blank line is entered
+fn input_sources() -> Result<Vec<String>, AurdersError> {
+    let mut sources = Vec::new();
+
+    loop {
+        let mut source = String::new();
+        print_prompt("Source > ");
+        io::stdout().flush().unwrap();
+
+        read_input(&mut source)?;
+
+        let source = source.trim();
+
+        if source.is_empty() {
+            break;
+        }
+
+        // makepkg saves a download as the last part of its url, unless named as name::url
+        if is_remote(source)
+            && !source.contains("::")
+            && !is_sensible_filename(&source_filename(source))
+        {
+            let name = input_string_validated(
+                &format!(
+                    "{} would be saved as '{}', enter a file name to save it as instead",
+                    source,
+                    source_filename(source)
+                ),
+                "",
+                validate_source_name,
+            )?;
+
+            if !name.is_empty() {
+                sources.push(format!("{}::{}", name, source));
+                continue;
+            }
+        }
+
+        sources.push(source.to_string());
+    }
+
+    Ok(sources)
+}
+
+/// is_sensible_filename tells whether a download saved as file would be recognizable, which is
+/// not the case for names without an extension (e.g. download) or with a query string
+pub fn is_sensible_filename(file: &str) -> bool {
+    !file.contains(['?', '&', '=']) && file.contains('.') && !file.starts_with('.')
+}
+
+/// validate_source_name validates the file name a source is saved as, in the name::url form.
+/// Blank is accepted as keeping the name makepkg picks.
+pub fn validate_source_name(name: &str) -> Result<(), String> {
+    if name.contains(['/', ':']) || name.chars().any(char::is_whitespace) {
+        return Err(format!(
+            "Invalid file name '{}': it cannot contain slashes, colons or whitespace.",
+            name
+        ));
+    }
+
+    Ok(())
+}
+
+/// ARCHIVE_EXTENSIONS are the extensions of files makepkg extracts, compressed tarballs (.tar.gz,
+/// ...) included
+const ARCHIVE_EXTENSIONS: &[&str] = &[
+    ".tar", ".tgz", ".tbz2", ".txz", ".tzst", ".zip", ".7z", ".rar", ".gz", ".bz2", ".xz", ".zst",
+    ".lz", ".lz4", ".lzma", ".deb", ".rpm",
+];
+
+/// is_archive tells whether file is one makepkg extracts, judging by its name
+pub fn is_archive(file: &str) -> bool {
+    ARCHIVE_EXTENSIONS
+        .iter()
+        .any(|extension| file.ends_with(extension))
+}
+
+/// is_signature tells whether file is a detached PGP signature makepkg verifies, judging by its
+/// name
+pub fn is_signature(file: &str) -> bool {
+    [".sig", ".asc", ".sign"]
+        .iter()
+        .any(|extension| file.ends_with(extension))
+}
+
+/// validate_fingerprint checks that fingerprint is a full PGP key fingerprint, 40 hexadecimal
+/// digits, which is all makepkg accepts in validpgpkeys
+pub fn validate_fingerprint(fingerprint: &str) -> Result<(), String> {
+    if fingerprint.len() == 40 && fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid fingerprint '{}': expected 40 hexadecimal digits without spaces.",
+            fingerprint
+        ))
+    }
+}
+
+/// validate_pkgname checks pkgname against the rules of makepkg, which only allows lowercase
+/// alphanumerics, '@', '.', '_', '+' and '-' in name, and does not allow it to start with a hyphen
+/// or period
+pub fn validate_pkgname(pkgname: &str) -> Result<(), String> {
+    if pkgname.is_empty() {
+        return Err("Invalid name: name cannot be empty.".to_string());
+    }
+
+    if pkgname.starts_with('-') || pkgname.starts_with('.') {
+        return Err(format!(
+            "Invalid name '{}': name cannot start with a hyphen or period.",
+            pkgname
+        ));
+    }
+
+    match pkgname.chars().find(|c| {
+        !(c.is_ascii_lowercase() || c.is_ascii_digit() || "@._+-".contains(*c))
+    }) {
+        Some(c) if c.is_uppercase() => Err(format!(
+            "Invalid name '{}': uppercase letters are not allowed.",
+            pkgname
+        )),
+        Some(c) => Err(format!(
+            "Invalid name '{}': '{}' is not allowed, use lowercase alphanumerics, '@', '.', '_', '+' or '-'.",
+            pkgname, c
+        )),
+        None => Ok(()),
+    }
+}
+
+/// validate_pkgver checks pkgver against the rules of makepkg, which only allows alphanumerics,
+/// periods, underscores and plus signs in version
+pub fn validate_pkgver(pkgver: &str) -> Result<(), String> {
+    if pkgver.is_empty() {
+        return Err("Invalid version: version cannot be empty.".to_string());
+    }
+
+    // epoch is a field of its own, colon would be mistaken for it
+    if pkgver.contains(':') {
+        return Err(format!(
+            "Invalid version '{}': colon is reserved for epoch.",
+            pkgver
+        ));
+    }
+
+    match pkgver
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '.' || *c == '_' || *c == '+'))
+    {
+        Some(c) => Err(format!(
+            "Invalid version '{}': '{}' is not allowed, use alphanumerics, '.', '_' or '+'.",
+            pkgver, c
+        )),
+        None => Ok(()),
+    }
+}
+
+/// validate_email checks that email has the basic shape of local@domain.tld
+pub fn validate_email(email: &str) -> bool {
+    if email.chars().any(|c| c.is_whitespace()) {
+        return false;
+    }
+
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && domain.split('.').all(|label| !label.is_empty())
+        }
+        None => false,
+    }
+}
+
+/// validate_url checks that url is empty (url is optional) or an http(s) URL with a host
+pub fn validate_url(url: &str) -> Result<(), String> {
+    if url.is_empty() {
+        return Ok(());
+    }
+
+    if url.chars().any(|c| c.is_whitespace()) {
+        return Err(format!("Invalid url '{}': url cannot contain spaces.", url));
+    }
+
+    let rest = match url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+    {
+        Some(rest) => rest,
+        None => {
+            return Err(format!(
+                "Invalid url '{}': expected it to start with http:// or https://.",
+                url
+            ))
+        }
+    };
+
+    // host ends where path, query or fragment begins, user info and port are allowed around it
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or("");
+    let host = host.split(':').next().unwrap_or("");
+    if host.is_empty() || host.split('.').any(|label| label.is_empty()) {
+        return Err(format!(
+            "Invalid url '{}': host is missing or malformed.",
+            url
+        ));
+    }
+
+    Ok(())
+}
+
+/// validate_arch checks the comma- or space-separated architectures in arch, which makepkg only
+/// allows to be made of lowercase alphanumerics and underscores, "any" not being combined with any
+/// other
+pub fn validate_arch(arch: &str) -> Result<(), String> {
+    let archs = parse_list(arch);
+
+    if let Some(invalid) = archs.iter().find(|arch| {
+        !arch
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    }) {
+        return Err(format!(
+            "Invalid architecture '{}': only lowercase letters, digits and '_' are allowed.",
+            invalid
+        ));
+    }
+
+    if archs.len() > 1 && archs.iter().any(|arch| arch == "any") {
+        return Err(
+            "Invalid architectures: 'any' cannot be combined with other architectures.".to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+/// validate_pkgrel checks that pkgrel is a positive integer, optionally followed by a single
+/// decimal part (e.g. 1.1) as makepkg allows
+pub fn validate_pkgrel(pkgrel: &str) -> Result<(), String> {
+    let is_number = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+
+    let (release, minor) = match pkgrel.split_once('.') {
+        Some((release, minor)) => (release, Some(minor)),
+        None => (pkgrel, None),
+    };
+
+    let valid =
+        is_number(release) && release.chars().any(|c| c != '0') && minor.is_none_or(is_number);
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid release number '{}': must be a positive integer, like 1 or 1.1.",
+            pkgrel
+        ))
+    }
+}
+
+/// validate_epoch checks that epoch is either blank or a non-negative integer
+pub fn validate_epoch(epoch: &str) -> Result<(), String> {
+    if epoch.is_empty() || epoch.chars().all(|c| c.is_ascii_digit()) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid epoch '{}': epoch must be a number.",
+            epoch
+        ))
+    }
+}
+
+/// KNOWN_LICENSES are the license identifiers Arch recognizes, SPDX ones along with the legacy
+/// names still found in many PKGBUILDs
+const KNOWN_LICENSES: [&str; 34] = [
+    "AGPL-3.0-only",
+    "AGPL-3.0-or-later",
+    "Apache-2.0",
+    "Artistic-2.0",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "BSL-1.0",
+    "CC0-1.0",
+    "CDDL-1.0",
+    "EPL-2.0",
+    "GPL-2.0-only",
+    "GPL-2.0-or-later",
+    "GPL-3.0-only",
+    "GPL-3.0-or-later",
+    "ISC",
+    "LGPL-2.1-only",
+    "LGPL-2.1-or-later",
+    "LGPL-3.0-only",
+    "LGPL-3.0-or-later",
+    "MIT",
+    "MPL-2.0",
+    "Unlicense",
+    "Zlib",
+    "custom",
+    "AGPL3",
+    "Apache",
+    "BSD",
+    "GPL",
+    "GPL2",
+    "GPL3",
+    "LGPL",
+    "LGPL2.1",
+    "LGPL3",
+    "MPL2",
+];
+
+/// COMMON_LICENSES are the licenses most packages use, with a note on each, in the order they are
+/// listed and numbered by print_licenses
+pub const COMMON_LICENSES: [(&str, &str); 16] = [
+    ("MIT", "permissive, short and simple"),
+    ("Apache-2.0", "permissive, with an explicit patent grant"),
+    ("BSD-2-Clause", "permissive, simplified BSD"),
+    (
+        "BSD-3-Clause",
+        "permissive, BSD with a non-endorsement clause",
+    ),
+    ("ISC", "permissive, functionally the same as MIT"),
+    ("Zlib", "permissive, common for libraries and games"),
+    ("GPL-2.0-only", "copyleft, version 2 of GPL only"),
+    (
+        "GPL-2.0-or-later",
+        "copyleft, version 2 of GPL or any later one",
+    ),
+    ("GPL-3.0-only", "copyleft, version 3 of GPL only"),
+    (
+        "GPL-3.0-or-later",
+        "copyleft, version 3 of GPL or any later one",
+    ),
+    ("LGPL-2.1-or-later", "weak copyleft, for libraries"),
+    ("LGPL-3.0-or-later", "weak copyleft, for libraries"),
+    (
+        "AGPL-3.0-or-later",
+        "copyleft, also covering use over a network",
+    ),
+    ("MPL-2.0", "weak copyleft, per file"),
+    ("Unlicense", "public domain dedication"),
+    (
+        "custom",
+        "anything else, install the license file to /usr/share/licenses/$pkgname",
+    ),
+];
+
+/// print_licenses prints the common licenses, numbered as the license prompt takes them
+pub fn print_licenses() {
+    println!("Common license identifiers (SPDX), any other one can be entered as it is:\n");
+    for (index, (license, note)) in COMMON_LICENSES.iter().enumerate() {
+        println!("  [{:>2}] {:<20}{}", index + 1, license, note);
+    }
+}
+
+/// resolve_licenses replaces the numbers among the comma- or space-separated licenses with the
+/// common licenses they refer to, as listed by print_licenses, returning them separated by spaces
+pub fn resolve_licenses(licenses: &str) -> String {
+    parse_list(licenses)
+        .into_iter()
+        .map(|license| match license.parse::<usize>() {
+            Ok(number) if (1..=COMMON_LICENSES.len()).contains(&number) => {
+                COMMON_LICENSES[number - 1].0.to_string()
+            }
+            _ => license,
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// KNOWN_ARCHS are the architectures Arch Linux and its ports (Arch Linux ARM, Arch Linux 32,
+/// Arch Linux RISC-V, ...) build packages for
+const KNOWN_ARCHS: &[&str] = &[
+    "any",
+    "x86_64",
+    "x86_64_v3",
+    "i486",
+    "i686",
+    "pentium4",
+    "aarch64",
+    "armv7h",
+    "armv6h",
+    "arm",
+    "riscv64",
+    "loong64",
+    "powerpc64le",
+    "powerpc64",
+    "powerpc",
+];
+
+/// unknown_archs returns the architectures that no port of Arch builds packages for
+pub fn unknown_archs(archs: &[String]) -> Vec<String> {
+    archs
+        .iter()
+        .filter(|arch| !KNOWN_ARCHS.contains(&arch.as_str()))
+        .cloned()
+        .collect()
+}
+
+/// unknown_licenses returns the licenses that are not recognized by Arch, custom:NAME and
+/// LicenseRef-NAME being recognized
+pub fn unknown_licenses(licenses: &[String]) -> Vec<String> {
+    licenses
+        .iter()
+        .filter(|license| {
+            !(KNOWN_LICENSES.contains(&license.as_str())
+                || license.starts_with("custom:")
+                || license.starts_with("LicenseRef-"))
+        })
+        .cloned()
+        .collect()
+}
+
+/// PKGDESC_MAX_LENGTH is the length of pkgdesc past which it is no longer a short description
+const PKGDESC_MAX_LENGTH: usize = 80;
+
+/// pkgdesc_warnings returns what is wrong with pkgdesc as per the package guidelines, which want
+/// it short and not starting with the name of package, one of names. None of it is an error.
+pub fn pkgdesc_warnings(pkgdesc: &str, names: &[String]) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    let length = pkgdesc.chars().count();
+    if length > PKGDESC_MAX_LENGTH {
+        warnings.push(format!(
+            "description is {} characters long, keep it under {}.",
+            length, PKGDESC_MAX_LENGTH
+        ));
+    }
+
+    let lowercase = pkgdesc.to_lowercase();
+    if let Some(name) = names.iter().find(|name| {
+        lowercase
+            .strip_prefix(name.as_str())
+            .is_some_and(|rest| rest.is_empty() || !rest.starts_with(|c: char| c.is_alphanumeric()))
+    }) {
+        warnings.push(format!(
+            "description starts with the package name '{}', which is redundant.",
+            name
+        ));
+    }
+
+    warnings
+}
+
+/// LICENSE_FINGERPRINTS maps phrases found in the text of common licenses to their identifier, in
+/// the order they are tried. Text alone does not tell whether later versions of GPL are allowed,
+/// "or-later" being the more common choice.
+const LICENSE_FINGERPRINTS: [(&[&str], &str); 13] = [
+    (
+        &["gnu affero general public license", "version 3"],
+        "AGPL-3.0-or-later",
+    ),
+    (
+        &["gnu lesser general public license", "version 3"],
+        "LGPL-3.0-or-later",
+    ),
+    (
+        &["gnu lesser general public license", "version 2.1"],
+        "LGPL-2.1-or-later",
+    ),
+    (
+        &["gnu general public license", "version 3"],
+        "GPL-3.0-or-later",
+    ),
+    (
+        &["gnu general public license", "version 2"],
+        "GPL-2.0-or-later",
+    ),
+    (&["apache license", "version 2.0"], "Apache-2.0"),
+    (&["mozilla public license version 2.0"], "MPL-2.0"),
+    (&["permission is hereby granted, free of charge"], "MIT"),
+    (
+        &["permission to use, copy, modify, and/or distribute this software"],
+        "ISC",
+    ),
+    (
+        &[
+            "redistribution and use in source and binary forms",
+            "neither the name",
+        ],
+        "BSD-3-Clause",
+    ),
+    (
+        &["redistribution and use in source and binary forms"],
+        "BSD-2-Clause",
+    ),
+    (
+        &["this is free and unencumbered software released into the public domain"],
+        "Unlicense",
+    ),
+    (&["boost software license - version 1.0"], "BSL-1.0"),
+];
+
+/// license_files returns the LICENSE or COPYING files of source_dir (LICENSE-MIT, COPYING.txt,
+/// ...), sorted by name
+pub fn license_files(source_dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(source_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name.to_uppercase(),
+                None => return false,
+            };
+            path.is_file()
+                && ["LICENSE", "LICENCE", "COPYING"]
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+        })
+        .collect();
+    files.sort();
+
+    files
+}
+
+/// detect_license guesses the license of source_dir from the text of its LICENSE or COPYING
+/// files (LICENSE-MIT, COPYING.txt, ...). Returns the identifiers found, separated by spaces.
+pub fn detect_license(source_dir: &Path) -> Option<String> {
+    let mut licenses: Vec<&str> = Vec::new();
+    for file in license_files(source_dir) {
+        let text = match fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        // line breaks and indentation differ between copies of the same license
+        let text = text
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .to_lowercase();
+
+        let license = LICENSE_FINGERPRINTS
+            .iter()
+            .find(|(phrases, _)| phrases.iter().all(|phrase| text.contains(phrase)));
+        if let Some((_, license)) = license {
+            if !licenses.contains(license) {
+                licenses.push(license);
+            }
+        }
+    }
+
+    if licenses.is_empty() {
+        None
+    } else {
+        Some(licenses.join(" "))
+    }
+}
+
+/// KNOWN_OPTIONS are the options makepkg understands, each of them can be negated with !
+const KNOWN_OPTIONS: [&str; 9] = [
+    "strip",
+    "docs",
+    "libtool",
+    "staticlibs",
+    "emptydirs",
+    "zipman",
+    "purge",
+    "debug",
+    "lto",
+];
+
+/// unknown_options returns the options which are not among KNOWN_OPTIONS, negated or not
+pub fn unknown_options(options: &[String]) -> Vec<String> {
+    options
+        .iter()
+        .filter(|option| !KNOWN_OPTIONS.contains(&option.trim_start_matches('!')))
+        .cloned()
+        .collect()
+}
+
+/// parse_list splits a comma- or space-separated input into its non-empty entries
+pub fn parse_list(input: &str) -> Vec<String> {
+    input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| entry.to_string())
+        .collect()
+}
+
+/// parse_backup splits backup files into entries like parse_list, making them relative to / as
+/// pacman expects
+pub fn parse_backup(input: &str) -> Vec<String> {
+    parse_list(input)
+        .iter()
+        .map(|entry| entry.trim_start_matches('/').to_string())
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+/// parse_depends splits dependencies into entries like parse_list, joining version constraints
+/// typed with spaces (e.g. "glibc >= 2.34") back to their dependency
+pub fn parse_depends(input: &str) -> Vec<String> {
+    let is_operator = |c: char| c == '<' || c == '>' || c == '=';
+    let mut depends: Vec<String> = Vec::new();
+
+    for token in parse_list(input) {
+        match depends.last_mut() {
+            Some(last) if token.starts_with(is_operator) || last.ends_with(is_operator) => {
+                last.push_str(&token)
+            }
+            _ => depends.push(token),
+        };
+    }
+
+    depends
+}
+
+/// parse_optdepends splits semicolon- or newline-separated optional dependencies into entries,
+/// keeping the spaces inside each "foo: does X" description intact
+pub fn parse_optdepends(input: &str) -> Vec<String> {
+    input
+        .split([';', '\n'])
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| entry.to_string())
+        .collect()
+}
+
+/// single_quote quotes value in single quotes, closing and reopening them around every single
+/// quote value contains, so that bash takes all of it literally
+pub fn single_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// escape_double_quoted escapes the characters bash treats specially inside double quotes (\, ",
+/// $ and `), so that value is kept as it is
+pub fn escape_double_quoted(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if matches!(c, '\\' | '"' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// quote_array quotes every value and joins them, ready to be placed inside a bash array
+pub fn quote_array(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| single_quote(value))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// optional_value returns the value as the only entry for fill_lines, or nothing if it is blank
+pub fn optional_value(value: &str) -> Vec<String> {
+    if value.is_empty() {
+        Vec::new()
+    } else {
+        vec![value.to_string()]
+    }
+}
+
+/// format_array quotes every value with quote and places each of them on a line of its own,
+/// indented to line up under the first value of the array. Double quoted values are left as they
+/// are, for their variables to be expanded.
+pub fn format_array(values: &[String], quote: char, indent: usize) -> String {
+    values
+        .iter()
+        .map(|value| match quote {
+            '\'' => single_quote(value),
+            _ => format!("{}{}{}", quote, value, quote),
+        })
+        .collect::<Vec<String>>()
+        .join(&format!("\n{}", " ".repeat(indent)))
+}
+
+/// DIFF_CONTEXT is the number of unchanged lines unified_diff shows around every change
+const DIFF_CONTEXT: usize = 3;
+
+/// unified_diff returns the difference of old and new as a unified diff (the format of diff -u),
+/// old_name and new_name being the names of files in its header. Empty when they are the same.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] is the length of longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // every line of both, marked as kept (' '), removed ('-') or added ('+')
+    let mut lines: Vec<(char, &str)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(('-', old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', new[j]));
+            j += 1;
+        }
+    }
+
+    let changes: Vec<usize> = (0..lines.len())
+        .filter(|&index| lines[index].0 != ' ')
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // changes close enough for their context to overlap share a hunk
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &index in &changes {
+        let start = index.saturating_sub(DIFF_CONTEXT);
+        let end = (index + DIFF_CONTEXT + 1).min(lines.len());
+
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut diff = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (start, end) in hunks {
+        let count = |range: &[(char, &str)], side: char| {
+            range
+                .iter()
+                .filter(|(kind, _)| *kind == ' ' || *kind == side)
+                .count()
+        };
+        let (old_before, new_before) = (count(&lines[..start], '-'), count(&lines[..start], '+'));
+        let (old_len, new_len) = (
+            count(&lines[start..end], '-'),
+            count(&lines[start..end], '+'),
+        );
+
+        // an empty range starts at the line before it
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_before + usize::from(old_len > 0),
+            old_len,
+            new_before + usize::from(new_len > 0),
+            new_len
+        ));
+        for (kind, line) in &lines[start..end] {
+            diff.push_str(&format!("{}{}\n", kind, line));
+        }
+    }
+
+    diff
+}
+
+/// fill_lines repeats every template line containing placeholder once per value, substituting the
+/// value in each copy. The line is dropped altogether when there are no values.
+pub fn fill_lines(template: &str, placeholder: &str, values: &[String]) -> String {
+    let mut filled = String::new();
+
+    for line in template.split_inclusive('\n') {
+        if line.contains(placeholder) {
+            if values.is_empty() {
+                debug!("{} -> (line dropped)", placeholder);
+            }
+            for value in values {
+                debug!("{} -> {}", placeholder, value);
+                filled.push_str(&line.replace(placeholder, value));
+            }
+        } else {
+            filled.push_str(line);
+        }
+    }
+
+    filled
+}
+
+/// fill_placeholders substitutes every {name} in template with its value in values, in a single
+/// pass so that a value which happens to contain a placeholder (e.g. {url} in pkgdesc) is kept as
+/// it is. Unknown placeholders are left untouched.
+pub fn fill_placeholders(template: &str, values: &[(&str, &str)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest.find('}').and_then(|end| {
+            values
+                .iter()
+                .find(|(name, _)| *name == &rest[1..end])
+                .map(|(_, value)| (end, *value))
+        });
+
+        match value {
+            Some((end, value)) => {
+                debug!("{} -> {}", &rest[..=end], value);
+                filled.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    filled.push_str(rest);
+    filled
+}
+
+/// is_placeholder_name returns whether name can be a {name} placeholder of templates: lowercase
+/// letters, digits and underscores
+pub fn is_placeholder_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// unknown_placeholders returns the {name} placeholders of template that are not in known, each
+/// once. Shell expansions like ${name} are not placeholders and are skipped.
+pub fn unknown_placeholders(template: &str, known: &[&str]) -> Vec<String> {
+    let mut unknown: Vec<String> = Vec::new();
+
+    for (start, _) in template.match_indices('{') {
+        if template[..start].ends_with('$') {
+            continue;
+        }
+
+        let rest = &template[start + 1..];
+        let name = match rest.find('}') {
+            Some(end) => &rest[..end],
+            None => continue,
+        };
+
+        if is_placeholder_name(name) && !known.contains(&name) && !unknown.iter().any(|u| u == name)
+        {
+            unknown.push(name.to_string());
+        }
+    }
+
+    unknown
+}
+
+/// get_vcs_source gets the git repository and optionally the branch to build from, and returns
+/// them as a git source cloned into a directory named after the package without -git suffix
+pub fn get_vcs_source(url: &str) -> Result<String, AurdersError> {
+    let repository = input_string("Enter the git repository of package", url)?;
+    let branch = input_string(
+        "Enter the branch to build from, none for the default branch",
+        "",
+    )?;
+
+    let mut source = format!("${{pkgname%-git}}::git+{}", repository);
+
+    if !branch.is_empty() {
+        source.push_str(&format!("#branch={}", branch));
+    }
+
+    Ok(source)
+}
+
+/// suggest_source proposes the release archive of repository at url as source, for the forges
+/// it recognizes (GitHub and GitLab). Tags are assumed to be in the form of v$pkgver. Name and
+/// version of package are left to $pkgname and $pkgver rather than taken as arguments, so the
+/// source keeps pointing at the right release when the package is bumped.
+pub fn suggest_source(url: &str) -> Option<String> {
+    let url = url.trim().trim_end_matches('/').trim_end_matches(".git");
+
+    let (forge, path) = if let Some(path) = url.strip_prefix("https://github.com/") {
+        ("github", path)
+    } else if let Some(path) = url.strip_prefix("https://gitlab.com/") {
+        ("gitlab", path)
+    } else {
+        return None;
+    };
+
+    // only the repository itself can be pointed at, i.e. owner/repo
+    let parts: Vec<&str> = path.split('/').collect();
+    if parts.len() != 2 || parts.iter().any(|part| part.is_empty()) {
+        return None;
+    }
+
+    let archive = match forge {
+        "github" => format!("{}/archive/v$pkgver.tar.gz", url),
+        _ => format!("{}/-/archive/v$pkgver/{}-v$pkgver.tar.gz", url, parts[1]),
+    };
+
+    Some(format!("$pkgname-$pkgver.tar.gz::{}", archive))
+}
+
+/// get_arch returns the current architecture, as Arch Linux names it
+pub fn get_arch() -> Result<String, AurdersError> {
     let arch = match env::consts::ARCH {
         "x86_64" => "x86_64",
         // *Untested*
         "x86" => "i686", // arch dropped support in 2017, unofficial port is available
         "arm" => "arm",  // unofficial port is available
         "aarch64" => "aarch64", // again, unofficial port is available (ARM)
-        _ => {
-            eprintln!("Architecture is not supported by Arch Linux.");
-            eprintln!("You might want to modify the file name of package (.pkg.tar.zst).");
-            dead_probably();
-            "UNSUPPORTED"
-        }
+        other => return Err(AurdersError::UnsupportedArch(other.to_string())),
     };
 
-    return arch.to_string();
+    return Ok(arch.to_string());
+}
+
+/// default_arch returns the architecture packages are built for by default, that of this machine
+/// or x86_64 when Arch Linux does not run on it
+pub fn default_arch() -> String {
+    get_arch().unwrap_or_else(|_| "x86_64".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkgver_accepts_dotted_version() {
+        assert!(validate_pkgver("1.0.0").is_ok());
+    }
+
+    #[test]
+    fn pkgver_rejects_hyphen() {
+        assert!(validate_pkgver("2.0-beta").is_err());
+    }
+
+    #[test]
+    fn pkgver_rejects_epoch_colon() {
+        let error = validate_pkgver("1:2.3").unwrap_err();
+        assert!(error.contains("colon"));
+    }
+
+    #[test]
+    fn email_accepts_local_at_domain() {
+        assert!(validate_email("a@b.com"));
+    }
+
+    #[test]
+    fn email_rejects_missing_at() {
+        assert!(!validate_email("foo"));
+    }
+
+    #[test]
+    fn email_rejects_missing_tld() {
+        assert!(!validate_email("a@b"));
+    }
+
+    #[test]
+    fn pkgrel_accepts_integer() {
+        assert!(validate_pkgrel("1").is_ok());
+    }
+
+    #[test]
+    fn pkgrel_accepts_decimal_part() {
+        assert!(validate_pkgrel("2.5").is_ok());
+    }
+
+    #[test]
+    fn pkgrel_rejects_zero() {
+        assert!(validate_pkgrel("0").is_err());
+    }
+
+    #[test]
+    fn pkgrel_rejects_negative() {
+        assert!(validate_pkgrel("-1").is_err());
+    }
+
+    #[test]
+    fn pkgrel_rejects_text() {
+        assert!(validate_pkgrel("foo").is_err());
+    }
+
+    #[test]
+    fn backup_is_made_relative() {
+        assert_eq!(
+            parse_backup("/etc/foo.conf, etc/bar.conf"),
+            vec!["etc/foo.conf", "etc/bar.conf"]
+        );
+    }
+
+    #[test]
+    fn pkgname_rejects_uppercase() {
+        let error = validate_pkgname("My_Pkg").unwrap_err();
+        assert!(error.contains("uppercase"));
+        assert!(validate_pkgname(&"My_Pkg".to_lowercase()).is_ok());
+    }
+
+    #[test]
+    fn pkgname_rejects_leading_hyphen() {
+        assert!(validate_pkgname("-foo").is_err());
+    }
+
+    #[test]
+    fn pkgname_accepts_valid_name() {
+        assert!(validate_pkgname("valid-pkg_1").is_ok());
+    }
+
+    #[test]
+    fn url_accepts_https() {
+        assert!(validate_url("https://example.com").is_ok());
+    }
+
+    #[test]
+    fn url_rejects_ftp() {
+        assert!(validate_url("ftp://x").is_err());
+    }
+
+    #[test]
+    fn url_rejects_text() {
+        assert!(validate_url("not a url").is_err());
+    }
+
+    #[test]
+    fn unknown_placeholders_skip_shell_expansions() {
+        assert_eq!(
+            unknown_placeholders("{pkgname} {foo} ${pkgver} {foo}", &["pkgname"]),
+            vec!["foo"]
+        );
+    }
+
+    #[test]
+    fn sensible_filenames() {
+        assert!(is_sensible_filename("foo-1.0.tar.gz"));
+        assert!(!is_sensible_filename("download"));
+        assert!(!is_sensible_filename("download?id=1"));
+    }
+
+    #[test]
+    fn source_names() {
+        assert!(validate_source_name("foo-1.0.tar.gz").is_ok());
+        assert!(validate_source_name("").is_ok());
+        assert!(validate_source_name("foo/bar.tar.gz").is_err());
+        assert!(validate_source_name("foo:bar").is_err());
+        assert!(validate_source_name("foo bar").is_err());
+    }
+
+    #[test]
+    fn depends_keep_version_constraints() {
+        assert_eq!(
+            parse_depends("glibc >= 2.34, gtk3\npython>=3.11"),
+            vec!["glibc>=2.34", "gtk3", "python>=3.11"]
+        );
+    }
+
+    #[test]
+    fn diff_of_changed_line() {
+        assert_eq!(
+            unified_diff("a\nb\nc\n", "a\nB\nc\n", "old", "new"),
+            "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+    }
+
+    #[test]
+    fn diff_of_same_text_is_empty() {
+        assert!(unified_diff("a\nb\n", "a\nb\n", "old", "new").is_empty());
+    }
+
+    #[test]
+    fn placeholders_are_filled_once() {
+        assert_eq!(
+            fill_placeholders(
+                "{pkgdesc} {url} {unknown}",
+                &[("pkgdesc", "see {url}"), ("url", "https://example.com")]
+            ),
+            "see {url} https://example.com {unknown}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tarball_of_non_utf8_source_is_named_after_package() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join(OsStr::from_bytes(b"foo-\xff"));
+        fs::create_dir(&source).unwrap();
+
+        let tarball = create_tarball(
+            &source,
+            dir.path(),
+            "foo-1.0.0-1",
+            TarballFormat::Gzip,
+            6,
+            false,
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            Path::new(&tarball),
+            dir.path().join("foo-1.0.0-1.tar.gz").as_path()
+        );
+    }
+
+    #[test]
+    fn writable_directory_is_left_clean() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(check_writable(dir.path()).is_ok());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn missing_directory_is_not_writable() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(check_writable(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn tarball_keeps_files_and_leaves_out_vcs() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("foo");
+        fs::create_dir_all(source.join(".git")).unwrap();
+        fs::write(source.join("main.c"), "int main() {}\n").unwrap();
+        fs::write(source.join(".git").join("HEAD"), "ref: refs/heads/master\n").unwrap();
+
+        let tarball = create_tarball(
+            &source,
+            dir.path(),
+            "foo-1.0.0-1",
+            TarballFormat::Gzip,
+            6,
+            false,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            Path::new(&tarball),
+            dir.path().join("foo-1.0.0-1.tar.gz").as_path()
+        );
+
+        let unpacked = dir.path().join("unpacked");
+        Archive::new(GzDecoder::new(File::open(&tarball).unwrap()))
+            .unpack(&unpacked)
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(unpacked.join("foo-1.0.0-1").join("main.c")).unwrap(),
+            "int main() {}\n"
+        );
+        assert!(!unpacked.join("foo-1.0.0-1").join(".git").exists());
+    }
+
+    #[test]
+    fn paths_leaving_directory_do_not_stay_inside() {
+        assert!(stays_inside(Path::new("templates/PKGBUILD")));
+        assert!(!stays_inside(Path::new("../evil")));
+        assert!(!stays_inside(Path::new("templates/../../evil")));
+        assert!(!stays_inside(Path::new("/etc/passwd")));
+    }
+
+    #[test]
+    fn tarball_escaping_directory_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let tarball = dir.path().join("evil.tar.gz");
+
+        // Builder refuses to write such a path, so it goes into the header by hand
+        let data = b"pwned\n";
+        let mut header = tar::Header::new_gnu();
+        header.as_old_mut().name[..7].copy_from_slice(b"../evil");
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        let mut builder = Builder::new(GzEncoder::new(
+            File::create(&tarball).unwrap(),
+            Compression::default(),
+        ));
+        builder.append(&header, &data[..]).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let target = dir.path().join("target");
+        fs::create_dir(&target).unwrap();
+        let result = decompress_tarball(&tarball, &target);
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+        assert!(!dir.path().join("evil").exists());
+    }
+
+    #[test]
+    fn github_repository_suggests_release_archive() {
+        assert_eq!(
+            suggest_source("https://github.com/user/repo").as_deref(),
+            Some("$pkgname-$pkgver.tar.gz::https://github.com/user/repo/archive/v$pkgver.tar.gz")
+        );
+        assert_eq!(
+            suggest_source("https://github.com/user/repo.git/").as_deref(),
+            Some("$pkgname-$pkgver.tar.gz::https://github.com/user/repo/archive/v$pkgver.tar.gz")
+        );
+        assert_eq!(suggest_source("https://github.com/user"), None);
+        assert_eq!(suggest_source("https://example.com/user/repo"), None);
+    }
+
+    #[test]
+    fn best_compression_is_no_larger_than_fast() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("foo");
+        fs::create_dir(&source).unwrap();
+        let contents: String = (0..20000)
+            .map(|i| format!("{} {}\n", i, i * i % 977))
+            .collect();
+        fs::write(source.join("data.txt"), contents).unwrap();
+
+        for format in [TarballFormat::Gzip, TarballFormat::Xz, TarballFormat::Zstd] {
+            let size = |level: &str, compression: u32| {
+                let output = dir.path().join(level);
+                fs::create_dir_all(&output).unwrap();
+                let tarball =
+                    create_tarball(&source, &output, "foo", format, compression, false, &[])
+                        .unwrap();
+                fs::metadata(tarball).unwrap().len()
+            };
+
+            assert!(size("best", 9) <= size("fast", 1));
+        }
+    }
+
+    #[test]
+    fn zstd_levels_span_its_own_range() {
+        assert_eq!(TarballFormat::Zstd.level(1), 1);
+        assert_eq!(TarballFormat::Zstd.level(6), 3);
+        assert_eq!(TarballFormat::Zstd.level(9), 22);
+        assert_eq!(TarballFormat::Gzip.level(9), 9);
+        assert_eq!(TarballFormat::Xz.level(0), 0);
+    }
+
+    #[test]
+    fn tarball_keeps_executable_bit() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("foo");
+        fs::create_dir(&source).unwrap();
+        let script = source.join("run.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(source.join("README"), "foo\n").unwrap();
+
+        let tarball = create_tarball(
+            &source,
+            dir.path(),
+            "foo",
+            TarballFormat::Gzip,
+            6,
+            false,
+            &[],
+        )
+        .unwrap();
+
+        let mut archive = Archive::new(GzDecoder::new(File::open(&tarball).unwrap()));
+        let modes: Vec<(PathBuf, u32)> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                (
+                    entry.path().unwrap().into_owned(),
+                    entry.header().mode().unwrap(),
+                )
+            })
+            .collect();
+        let mode = |name: &str| {
+            modes
+                .iter()
+                .find(|(path, _)| path == Path::new("foo").join(name).as_path())
+                .map(|(_, mode)| *mode)
+                .unwrap()
+        };
+
+        assert_eq!(mode("run.sh") & 0o777, 0o755);
+        assert_eq!(mode("README") & 0o111, 0);
+    }
+
+    #[test]
+    fn filter_excludes_gitignore_like_patterns() {
+        let filter =
+            TarballFilter::new(false, &["target/".to_string(), "*.o".to_string()]).unwrap();
+
+        assert!(filter.excludes(Path::new("target"), true));
+        assert!(filter.excludes(Path::new("sub/target"), true));
+        assert!(!filter.excludes(Path::new("target"), false));
+        assert!(filter.excludes(Path::new("main.o"), false));
+        assert!(filter.excludes(Path::new("src/lib/main.o"), false));
+        assert!(!filter.excludes(Path::new("main.c"), false));
+        assert!(filter.excludes(Path::new(".git"), true));
+    }
+
+    #[test]
+    fn filter_reads_aurdersignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("foo");
+        fs::create_dir_all(source.join("build")).unwrap();
+        fs::write(source.join("build").join("out"), "out\n").unwrap();
+        fs::write(source.join("main.c"), "int main() {}\n").unwrap();
+        fs::write(source.join(".aurdersignore"), "# artifacts\nbuild/\n").unwrap();
+
+        let tarball = create_tarball(
+            &source,
+            dir.path(),
+            "foo",
+            TarballFormat::Gzip,
+            6,
+            false,
+            &[],
+        )
+        .unwrap();
+
+        let unpacked = dir.path().join("unpacked");
+        Archive::new(GzDecoder::new(File::open(&tarball).unwrap()))
+            .unpack(&unpacked)
+            .unwrap();
+        assert!(unpacked.join("foo").join("main.c").is_file());
+        assert!(!unpacked.join("foo").join("build").exists());
+    }
+
+    /// detected returns the license detect_license finds for a LICENSE file of text
+    fn detected(text: &str) -> Option<String> {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("LICENSE"), text).unwrap();
+        detect_license(dir.path())
+    }
+
+    #[test]
+    fn detects_mit_license() {
+        let text = "MIT License
+
+Copyright (c) 2024 Jane Doe
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the \"Software\"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software.
+";
+
+        assert_eq!(detected(text).as_deref(), Some("MIT"));
+    }
+
+    #[test]
+    fn detects_gpl_license() {
+        let text = "                    GNU GENERAL PUBLIC LICENSE
+                       Version 3, 29 June 2007
+
+ Copyright (C) 2007 Free Software Foundation, Inc. <https://fsf.org/>
+ Everyone is permitted to copy and distribute verbatim copies
+ of this license document, but changing it is not allowed.
+";
+
+        assert_eq!(detected(text).as_deref(), Some("GPL-3.0-or-later"));
+    }
+
+    #[test]
+    fn detects_apache_license() {
+        let text = "
+                                 Apache License
+                           Version 2.0, January 2004
+                        http://www.apache.org/licenses/
+
+   TERMS AND CONDITIONS FOR USE, REPRODUCTION, AND DISTRIBUTION
+";
+
+        assert_eq!(detected(text).as_deref(), Some("Apache-2.0"));
+    }
+
+    #[test]
+    fn detects_bsd_licenses() {
+        let two_clause = "Copyright (c) 2024, Jane Doe
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice, this
+   list of conditions and the following disclaimer.
+";
+        let three_clause = format!(
+            "{}
+3. Neither the name of the copyright holder nor the names of its
+   contributors may be used to endorse or promote products derived from
+   this software without specific prior written permission.
+",
+            two_clause
+        );
+
+        assert_eq!(detected(two_clause).as_deref(), Some("BSD-2-Clause"));
+        assert_eq!(detected(&three_clause).as_deref(), Some("BSD-3-Clause"));
+    }
+
+    #[test]
+    fn unknown_license_text_is_not_detected() {
+        assert_eq!(detected("All rights reserved.\n"), None);
+    }
+
+    #[test]
+    fn strict_input_skips_blank_lines() {
+        let input = read_strict("Enter the name of maintainer", &b"\n   \n Jane Doe \n"[..]);
+
+        assert_eq!(input.unwrap().as_deref(), Some("Jane Doe"));
+    }
+
+    #[test]
+    fn strict_input_stops_at_end_of_input() {
+        let input = read_strict("Enter the name of maintainer", &b"\n"[..]);
+
+        assert_eq!(input.unwrap(), None);
+    }
+
+    #[test]
+    fn long_pkgdesc_is_warned_about() {
+        let names = vec!["foo".to_string()];
+        let long = "a".repeat(120);
+
+        let warnings = pkgdesc_warnings(&long, &names);
+
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("description is 120 characters long"));
+        assert!(pkgdesc_warnings("A tool to do things", &names).is_empty());
+        assert_eq!(pkgdesc_warnings("Foo, a tool", &names).len(), 1);
+    }
+
+    #[test]
+    fn manual_arches_end_up_quoted() {
+        for (entered, array) in [
+            ("x86_64", "arch=('x86_64')"),
+            (
+                " x86_64, aarch64 armv7h",
+                "arch=('x86_64' 'aarch64' 'armv7h')",
+            ),
+        ] {
+            assert!(validate_arch(entered).is_ok());
+
+            let pkginfo = crate::shared::Information {
+                arch: parse_list(entered),
+                ..crate::shared::tests::information()
+            };
+            let pkgbuild =
+                crate::pkgbuild::generate_pkgbuild(&pkginfo, &Templates::default()).unwrap();
+            assert!(pkgbuild.contains(&format!("\n{}\n", array)), "{}", entered);
+        }
+    }
+
+    #[test]
+    fn invalid_manual_arches_are_rejected() {
+        assert!(validate_arch("x86-64").is_err());
+        assert!(validate_arch("any x86_64").is_err());
+        assert!(validate_arch("X86_64").is_err());
+    }
 }
@@ -0,0 +1,146 @@
+//! roundtrip tests generate the PKGBUILD of representative packages and have makepkg parse it,
+//! catching the quoting and array mistakes that comparing strings misses. They are built with the
+//! makepkg feature and skipped when makepkg is not installed.
+#![cfg(feature = "makepkg")]
+
+use std::fs;
+use std::process::Command;
+
+use aurders::utils::find_program;
+use aurders::{generate_pkgbuild, ArchSource, Information, Templates};
+
+/// printsrcinfo writes the PKGBUILD of pkginfo to a temporary directory and returns what
+/// makepkg --printsrcinfo prints for it, failing when makepkg does not exit zero. None when makepkg
+/// is not installed.
+fn printsrcinfo(pkginfo: &Information) -> Option<String> {
+    if find_program("makepkg").is_none() {
+        eprintln!("Warning: makepkg is not installed, skipping.");
+        return None;
+    }
+
+    let pkgbuild = generate_pkgbuild(pkginfo, &Templates::default()).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("PKGBUILD"), &pkgbuild).unwrap();
+
+    let output = Command::new("makepkg")
+        .arg("--printsrcinfo")
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "makepkg rejected PKGBUILD:\n{}\n{}",
+        pkgbuild,
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    Some(String::from_utf8(output.stdout).unwrap())
+}
+
+/// information returns the information of a plain package built with make
+fn information() -> Information {
+    Information {
+        maintainer_name: "Jane Doe".to_string(),
+        maintainer_email: "jane@example.com".to_string(),
+        pkgbase: "foo".to_string(),
+        pkgname: vec!["foo".to_string()],
+        pkgver: "1.0.0".to_string(),
+        pkgrel: "1".to_string(),
+        pkgdesc: "A foo tool".to_string(),
+        url: "https://example.com/foo".to_string(),
+        license: "MIT".to_string(),
+        arch: vec!["x86_64".to_string()],
+        depends: "glibc".to_string(),
+        makedepends: "cmake".to_string(),
+        source: vec!["foo-$pkgver.tar.gz".to_string()],
+        checksums: vec!["abc123".to_string()],
+        build: "make".to_string(),
+        package: vec!["make DESTDIR=\"$pkgdir\" install".to_string()],
+        ..Information::default()
+    }
+}
+
+#[test]
+fn plain_package() {
+    let Some(srcinfo) = printsrcinfo(&information()) else {
+        return;
+    };
+
+    assert!(srcinfo.contains("pkgbase = foo\n"));
+    assert!(srcinfo.contains("\tpkgver = 1.0.0\n"));
+    assert!(srcinfo.contains("\tsource = foo-1.0.0.tar.gz\n"));
+    assert!(srcinfo.contains("\tsha256sums = abc123\n"));
+}
+
+#[test]
+fn values_with_shell_characters() {
+    let pkginfo = Information {
+        pkgdesc: "Says \"hi\" for $5 with `echo` and 'quotes'".to_string(),
+        optdepends: "bar: does X; baz: for 'quoted' things".to_string(),
+        epoch: "1".to_string(),
+        ..information()
+    };
+
+    let Some(srcinfo) = printsrcinfo(&pkginfo) else {
+        return;
+    };
+
+    assert!(srcinfo.contains("\tpkgdesc = Says \"hi\" for $5 with `echo` and 'quotes'\n"));
+    assert!(srcinfo.contains("\toptdepends = bar: does X\n"));
+    assert!(srcinfo.contains("\toptdepends = baz: for 'quoted' things\n"));
+    assert!(srcinfo.contains("\tepoch = 1\n"));
+}
+
+#[test]
+fn split_package_with_arch_sources() {
+    let pkginfo = Information {
+        pkgname: vec!["foo".to_string(), "foo-docs".to_string()],
+        arch: vec!["x86_64".to_string(), "aarch64".to_string()],
+        arch_source: vec![
+            ArchSource {
+                arch: "x86_64".to_string(),
+                source: vec!["foo-x86_64.tar.gz".to_string()],
+                checksums: vec!["def456".to_string()],
+            },
+            ArchSource {
+                arch: "aarch64".to_string(),
+                source: vec!["foo-aarch64.tar.gz".to_string()],
+                checksums: vec!["789abc".to_string()],
+            },
+        ],
+        package: vec![
+            "make DESTDIR=\"$pkgdir\" install".to_string(),
+            "make DESTDIR=\"$pkgdir\" install-docs".to_string(),
+        ],
+        ..information()
+    };
+
+    let Some(srcinfo) = printsrcinfo(&pkginfo) else {
+        return;
+    };
+
+    assert!(srcinfo.contains("pkgname = foo\n"));
+    assert!(srcinfo.contains("pkgname = foo-docs\n"));
+    assert!(srcinfo.contains("\tsource_aarch64 = foo-aarch64.tar.gz\n"));
+    assert!(srcinfo.contains("\tsha256sums_x86_64 = def456\n"));
+}
+
+#[test]
+fn vcs_package() {
+    let pkginfo = Information {
+        pkgbase: "foo-git".to_string(),
+        pkgname: vec!["foo-git".to_string()],
+        makedepends: "cmake git".to_string(),
+        source: vec!["git+https://example.com/foo.git".to_string()],
+        checksums: vec!["SKIP".to_string()],
+        vcs: true,
+        ..information()
+    };
+
+    let Some(srcinfo) = printsrcinfo(&pkginfo) else {
+        return;
+    };
+
+    assert!(srcinfo.contains("\tsource = git+https://example.com/foo.git\n"));
+    assert!(srcinfo.contains("\tsha256sums = SKIP\n"));
+}